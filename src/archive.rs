@@ -0,0 +1,300 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::Context;
+use time::OffsetDateTime;
+use zip::ZipArchive;
+
+use crate::transforms::{Code, Diagnostics};
+
+pub(crate) const ZIP_EXT: &str = "zip";
+const TEXTPACK_EXT: &str = "textpack";
+const TEXTBUNDLE_EXT: &str = "textbundle";
+
+/// An export read straight from its `.zip` file, without extracting it
+///
+/// Entries are addressed by their path relative to the export, the same way the files of an
+/// unzipped export are relative to its directory. When every entry is inside a single folder,
+/// such as "Garden/" in "Garden.zip", that folder is the export.
+///
+/// TextPacks are read the same way, they are zipped TextBundles. The export of a TextPack is its
+/// single `.textbundle` folder, whatever its name and whatever else was zipped next to it.
+#[derive(Debug)]
+pub(crate) struct Archive {
+    path: PathBuf,
+    /// Every visible file by its path relative to the export
+    entries: BTreeMap<PathBuf, Entry>,
+    zip: Mutex<ZipArchive<File>>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    index: usize,
+    modified: OffsetDateTime,
+}
+
+impl Archive {
+    /// The input is a `.zip` file rather than a directory
+    pub fn is_archive(path: &Path) -> bool {
+        path.is_file() && path.extension().is_some_and(|ext| ext == ZIP_EXT)
+    }
+
+    /// Entries which can not be read are reported to `diagnostics` and left out
+    pub fn open(path: &Path, diagnostics: &mut Diagnostics) -> anyhow::Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open the archive = {}", path.display()))?;
+        let mut zip = ZipArchive::new(file)
+            .with_context(|| format!("Failed to read the archive = {}", path.display()))?;
+
+        let mut entries = BTreeMap::new();
+        for index in 0..zip.len() {
+            let entry = zip.by_index_raw(index)?;
+            // Most zip tools write UTF-8 names without setting the flag for them, which are
+            // otherwise decoded as CP437
+            let name = match std::str::from_utf8(entry.name_raw()) {
+                Ok(name) => PathBuf::from(name),
+                Err(_) => PathBuf::from(entry.name()),
+            };
+            // Entries escaping the archive with ".." or an absolute path are never read
+            let enclosed = name.components().all(|c| matches!(c, Component::Normal(_)));
+            if !enclosed {
+                diagnostics.warning(
+                    Code::UnsafeArchiveEntry,
+                    path,
+                    format!(
+                        "skipping the entry = {} because its path is outside of the archive",
+                        entry.name()
+                    ),
+                );
+                continue;
+            }
+            // Skip hidden files and macOS' "__MACOSX" resource forks, the same as the hidden
+            // files of an export directory
+            let hidden = name.components().any(|c| {
+                let c = c.as_os_str().to_string_lossy();
+                c.starts_with('.') || c == "__MACOSX"
+            });
+            if entry.is_dir() || hidden {
+                continue;
+            }
+            // Zip only records the local time of the modification, taken as UTC
+            let modified = entry
+                .last_modified()
+                .and_then(|dt| OffsetDateTime::try_from(dt).ok())
+                .unwrap_or(OffsetDateTime::UNIX_EPOCH);
+            entries.insert(name, Entry { index, modified });
+        }
+
+        // "Garden.zip" -> "Garden/Woodworking.md", "Note.textpack" -> "Note.textbundle/text.md"
+        let top_dirs: BTreeSet<PathBuf> = entries
+            .keys()
+            .filter(|name| name.components().count() > 1)
+            .filter_map(|name| name.components().next())
+            .map(|c| PathBuf::from(c.as_os_str()))
+            .collect();
+        let top_files = entries.keys().any(|name| name.components().count() == 1);
+        let is_pack = path.extension().is_some_and(|ext| ext == TEXTPACK_EXT);
+        let bundles: Vec<&PathBuf> = top_dirs
+            .iter()
+            .filter(|dir| is_pack && dir.extension().is_some_and(|ext| ext == TEXTBUNDLE_EXT))
+            .collect();
+        let root = match bundles[..] {
+            [bundle] => Some(bundle.clone()),
+            _ if top_dirs.len() == 1 && !top_files => top_dirs.first().cloned(),
+            _ => None,
+        };
+        if let Some(root) = root {
+            entries = entries
+                .into_iter()
+                .filter_map(|(name, entry)| {
+                    Some((name.strip_prefix(&root).ok()?.to_path_buf(), entry))
+                })
+                .collect();
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            entries,
+            zip: Mutex::new(zip),
+        })
+    }
+
+    /// Example: "Exports/Garden.zip"
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn contains(&self, name: &Path) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    /// Every file of the export in sorted order
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.entries.keys().map(PathBuf::as_path)
+    }
+
+    /// Every directory holding a file, zip files do not need to list them
+    pub fn directories(&self) -> BTreeSet<PathBuf> {
+        self.files()
+            .flat_map(Path::ancestors)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .filter(|dir| !self.entries.contains_key(*dir))
+            .map(Path::to_path_buf)
+            .collect()
+    }
+
+    /// When the file was last modified
+    pub fn modified(&self, name: &Path) -> anyhow::Result<OffsetDateTime> {
+        Ok(self.entry(name)?.modified)
+    }
+
+    pub fn read_to_string(&self, name: &Path) -> anyhow::Result<String> {
+        let mut body = String::new();
+        self.with_file(name, |file| file.read_to_string(&mut body).map(|_| ()))?;
+        Ok(body)
+    }
+
+    /// Stream the file to `destination` without holding all of it in memory
+    pub fn copy(&self, name: &Path, destination: &Path) -> anyhow::Result<()> {
+        let mut output = fs::File::create(destination)?;
+        self.with_file(name, |file| io::copy(file, &mut output).map(|_| ()))
+    }
+
+    fn entry(&self, name: &Path) -> anyhow::Result<&Entry> {
+        self.entries.get(name).with_context(|| {
+            format!(
+                "The archive = {} has no entry = {}",
+                self.path.display(),
+                name.display()
+            )
+        })
+    }
+
+    fn with_file(
+        &self,
+        name: &Path,
+        f: impl FnOnce(&mut zip::read::ZipFile<'_>) -> io::Result<()>,
+    ) -> anyhow::Result<()> {
+        let index = self.entry(name)?.index;
+        // A poisoned lock only means another read failed, the archive itself is unchanged
+        let mut zip = self.zip.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = zip.by_index(index)?;
+        f(&mut file).with_context(|| {
+            format!(
+                "Failed to read the entry = {} of the archive = {}",
+                name.display(),
+                self.path.display()
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{craft_files::Options, reader::TempDir, Craft, Reader, Space};
+    use std::io::Write;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    #[test]
+    fn craft_export_is_read_from_the_archive() {
+        let dir = TempDir::new("archive");
+        let path = dir.join("Garden.zip");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        for (name, contents) in [
+            ("Garden/Woodworking/Dovetail Joint.md", "# Dovetail Joint\n"),
+            ("Garden/Woodworking/Dovetail Joint.assets/plan.png", "png"),
+            ("Garden/.DS_Store", ""),
+            ("__MACOSX/Garden/._Woodworking", ""),
+        ] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let space = Space::new(path.clone());
+        assert_eq!(space.name, "Garden");
+        let scan = Craft.read(&space, &Options::default()).unwrap();
+        assert_eq!(scan.directories, vec![PathBuf::from("Woodworking")]);
+        let [note] = &scan.files[..] else {
+            panic!("expected a single note, found {:?}", scan.files);
+        };
+        assert_eq!(note.path_rel, PathBuf::from("Woodworking/Dovetail Joint"));
+        assert_eq!(note.body, "# Dovetail Joint\n");
+        assert_eq!(
+            note.assets_dir,
+            Some(path.join("Woodworking/Dovetail Joint.assets"))
+        );
+
+        let archive = &scan.archives[0];
+        let destination = dir.join("plan.png");
+        archive
+            .copy(
+                Path::new("Woodworking/Dovetail Joint.assets/plan.png"),
+                &destination,
+            )
+            .unwrap();
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "png");
+    }
+
+    #[test]
+    fn the_single_top_level_folder_is_the_export() {
+        let dir = TempDir::new("archive-roots");
+        let zip_with = |name: &str, entries: &[&str]| {
+            let path = dir.join(name);
+            let mut zip = ZipWriter::new(File::create(&path).unwrap());
+            for entry in entries {
+                zip.start_file(*entry, SimpleFileOptions::default())
+                    .unwrap();
+            }
+            zip.finish().unwrap();
+            let mut diagnostics = Diagnostics::default();
+            let archive = Archive::open(&path, &mut diagnostics).unwrap();
+            let codes: Vec<Code> = diagnostics.iter().map(|d| d.code).collect();
+            let unsafe_entries = entries.iter().filter(|e| e.starts_with("..")).count();
+            assert_eq!(codes, vec![Code::UnsafeArchiveEntry; unsafe_entries]);
+            archive
+        };
+        let files = |archive: &Archive| -> Vec<PathBuf> {
+            archive.files().map(Path::to_path_buf).collect()
+        };
+
+        let export = zip_with(
+            "Export.zip",
+            &[
+                "Garden/Woodworking.md",
+                "Garden/Saws.md",
+                "../Garden/evil.md",
+            ],
+        );
+        assert_eq!(
+            files(&export),
+            ["Saws.md", "Woodworking.md"].map(PathBuf::from)
+        );
+
+        let flat = zip_with("Flat.zip", &["Woodworking/Dovetail Joint.md", "Saws.md"]);
+        assert_eq!(
+            files(&flat),
+            ["Saws.md", "Woodworking/Dovetail Joint.md"].map(PathBuf::from)
+        );
+
+        let pack = zip_with(
+            "Saws.textpack",
+            &[
+                "Hand Saws.textbundle/text.md",
+                "Hand Saws.textbundle/assets/rip.png",
+                "Exported with Craft.txt",
+                "__MACOSX/Hand Saws.textbundle/._text.md",
+            ],
+        );
+        assert_eq!(
+            files(&pack),
+            ["assets/rip.png", "text.md"].map(PathBuf::from)
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{
+    backend::{self, link_destination, Backend, Written},
+    craft_files::{add_ext, CraftDocs, FileData, NoteType},
+    transforms::code_ranges,
+};
+
+lazy_static! {
+    // An autolink, which MDX would read as a JSX tag. It becomes a regular link.
+    //
+    // Example: <https://docs.astro.build>
+    static ref RE_AUTOLINK: Regex = Regex::new(r"^<((?:https?|mailto):[^<>\s]+)>").unwrap();
+    // An HTML comment, which is not allowed in MDX. It becomes a JSX comment.
+    //
+    // Example: <!-- TODO -->
+    static ref RE_HTML_COMMENT: Regex = Regex::new(r"(?s)^<!--(.*?)-->").unwrap();
+}
+
+/// Written next to the entries, Astro ignores files starting with '_' inside a collection
+const SCHEMA_FILE: &str = "_schema.ts";
+const MANIFEST_FILE: &str = "_manifest.json";
+
+/// The frontmatter of every entry as (key, Zod type), see Entry
+const SCHEMA: [(&str, &str); 7] = [
+    ("title", "z.string()"),
+    ("pubDate", "z.coerce.date()"),
+    ("updatedDate", "z.coerce.date()"),
+    ("weight", "z.number()"),
+    ("draft", "z.boolean().default(false)"),
+    ("aliases", "z.array(z.string()).default([])"),
+    (
+        "noteType",
+        "z.enum([\"evergreen\", \"potted\", \"seedling\", \"none\"])",
+    ),
+];
+
+/// Astro content collections, chosen with `--target astro` or `--target astro-mdx`
+///
+/// OUTPUT_DIR is the collection's directory, `src/content/<collection>/`. Next to the entries it
+/// writes `_schema.ts`, the Zod schema of the entries' frontmatter to use in
+/// `src/content/config.ts`, and `_manifest.json` listing every entry with its URL.
+///
+/// Links between notes are root-relative URLs, assuming the collection is routed at
+/// `/<collection>/[...slug]/`.
+///
+/// Example link: [Dovetail Joint](/garden/woodworking/dovetail-joint/#marking-out)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Astro {
+    /// Write `.mdx` entries instead of `.md`, see escape_mdx
+    pub mdx: bool,
+}
+
+impl Astro {
+    fn ext(&self) -> &str {
+        match self.mdx {
+            true => "mdx",
+            false => "md",
+        }
+    }
+}
+
+impl Backend for Astro {
+    fn name(&self) -> &str {
+        "astro"
+    }
+
+    fn content_dir(&self) -> &Path {
+        Path::new("src/content")
+    }
+
+    fn page_path(&self, page_slug: &Path, has_assets: bool) -> PathBuf {
+        match has_assets {
+            true => add_ext(&page_slug.join("index"), self.ext()),
+            false => add_ext(page_slug, self.ext()),
+        }
+    }
+
+    fn link(
+        &self,
+        mount: &str,
+        _from: &FileData,
+        to: &FileData,
+        text: &str,
+        header: Option<&str>,
+    ) -> String {
+        let url = self.page_url(Path::new(mount), to);
+        let destination = format!("{url}{}", header.unwrap_or_default());
+        format!("[{text}]({})", link_destination(&destination))
+    }
+
+    fn frontmatter(&self, note: &FileData) -> String {
+        let yaml = serde_yaml::to_string(&Entry::from(note))
+            .expect("The frontmatter of an entry to be serializable as YAML");
+        format!("---\n{yaml}---\n")
+    }
+
+    fn body(&self, body: String) -> String {
+        match self.mdx {
+            true => escape_mdx(&body),
+            false => body,
+        }
+    }
+
+    fn section_index_name(&self) -> Option<&str> {
+        None
+    }
+
+    fn section_index(&self, _title: &str, _root: bool) -> String {
+        String::new()
+    }
+
+    fn write_files(&self, craft_docs: &CraftDocs, output_dir: &Path) -> anyhow::Result<Written> {
+        let mut written = backend::write_files(self, craft_docs, output_dir)?;
+
+        let schema_path = output_dir.join(SCHEMA_FILE);
+        fs::write(&schema_path, schema(craft_docs.mount())).with_context(|| {
+            format!(
+                "Failed to write the collection's schema at path = {}",
+                schema_path.display()
+            )
+        })?;
+        written.sections.push(PathBuf::from(SCHEMA_FILE));
+
+        let mut manifest: Vec<ManifestEntry> = craft_docs
+            .files
+            .values()
+            .map(|note| ManifestEntry {
+                id: note.path_slug.display().to_string(),
+                url: self.page_url(Path::new(craft_docs.mount()), note),
+                data: Entry::from(note),
+                assets: note
+                    .assets
+                    .iter()
+                    .flatten()
+                    .map(|asset| asset.link())
+                    .collect(),
+            })
+            .collect();
+        manifest.sort_by(|a, b| a.id.cmp(&b.id));
+        let manifest_path = output_dir.join(MANIFEST_FILE);
+        let json = serde_json::to_string_pretty(&manifest)?;
+        fs::write(&manifest_path, json).with_context(|| {
+            format!(
+                "Failed to write the collection's manifest at path = {}",
+                manifest_path.display()
+            )
+        })?;
+        written.sections.push(PathBuf::from(MANIFEST_FILE));
+        written.sections.sort();
+        Ok(written)
+    }
+}
+
+/// The frontmatter of an entry, its keys and types are described by SCHEMA
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry<'a> {
+    title: &'a str,
+    pub_date: &'a str,
+    updated_date: &'a str,
+    weight: u32,
+    draft: bool,
+    aliases: &'a [String],
+    note_type: &'static str,
+}
+
+impl<'a> From<&'a FileData> for Entry<'a> {
+    fn from(note: &'a FileData) -> Self {
+        Self {
+            title: &note.name,
+            pub_date: &note.created_at,
+            updated_date: &note.modified_at,
+            weight: note.note_type.to_weight().to_digit(10).unwrap_or_default(),
+            draft: note.draft,
+            aliases: &note.aliases,
+            note_type: match note.note_type {
+                NoteType::Evergreen => "evergreen",
+                NoteType::Potted => "potted",
+                NoteType::Seedling => "seedling",
+                NoteType::None => "none",
+            },
+        }
+    }
+}
+
+/// An entry of `_manifest.json`
+#[derive(Debug, Serialize)]
+struct ManifestEntry<'a> {
+    /// The entry's path inside the collection
+    ///
+    /// Example: "woodworking/dovetail-joint/index.md"
+    id: String,
+    url: String,
+    data: Entry<'a>,
+    /// The entry's assets relative to the entry
+    assets: Vec<String>,
+}
+
+/// The contents of `_schema.ts`
+fn schema(collection: &str) -> String {
+    let fields: String = SCHEMA
+        .iter()
+        .map(|(key, zod)| format!("    {key}: {zod},\n"))
+        .collect();
+    format!(
+        "// Generated by craft-zola, do not edit.\n\
+        // Register it in src/content/config.ts: export const collections = {{ {collection} }};\n\
+        import {{ defineCollection, z }} from \"astro:content\";\n\
+        \n\
+        export const {collection} = defineCollection({{\n  \
+        type: \"content\",\n  \
+        schema: z.object({{\n\
+        {fields}  \
+        }}),\n\
+        }});\n",
+        collection = collection.replace(|c: char| !c.is_ascii_alphanumeric(), "_"),
+    )
+}
+
+/// MDX reads `{`, `}` and `<` as the start of JavaScript expressions and JSX tags, escape them
+/// everywhere but in code
+///
+/// Example:
+///     "Set {width} <https://astro.build>" -> "Set \{width\} [https://astro.build](https://astro.build)"
+fn escape_mdx(body: &str) -> String {
+    let mut escaped = String::with_capacity(body.len());
+    let mut last = 0;
+    for range in code_ranges(body) {
+        escaped.push_str(&escape_mdx_text(&body[last..range.start]));
+        escaped.push_str(&body[range.clone()]);
+        last = range.end;
+    }
+    escaped.push_str(&escape_mdx_text(&body[last..]));
+    escaped
+}
+
+fn escape_mdx_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next() {
+        let rest = &text[i..];
+        let before = &text[..i];
+        match c {
+            // Already escaped
+            _ if before.ends_with('\\') => escaped.push(c),
+            // A link destination wrapped in <>, see backend::link_destination
+            '<' if before.ends_with("](") => escaped.push(c),
+            '<' => {
+                if let Some(caps) = RE_AUTOLINK.captures(rest) {
+                    escaped.push_str(&format!("[{url}]({url})", url = &caps[1]));
+                    i += caps[0].len();
+                    continue;
+                }
+                if let Some(caps) = RE_HTML_COMMENT.captures(rest) {
+                    escaped.push_str(&format!("{{/*{}*/}}", &caps[1]));
+                    i += caps[0].len();
+                    continue;
+                }
+                escaped.push_str("\\<");
+            }
+            '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+        i += c.len_utf8();
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frontmatter_matches_the_schema() {
+        let note = FileData {
+            name: "Dovetail Joint".into(),
+            created_at: "2023-01-04".into(),
+            modified_at: "2023-02-01".into(),
+            note_type: NoteType::Evergreen,
+            ..Default::default()
+        };
+        let frontmatter = Astro::default().frontmatter(&note);
+        let yaml = frontmatter.trim_matches(|c| c == '-' || c == '\n');
+        let mapping: serde_yaml::Mapping = serde_yaml::from_str(yaml).unwrap();
+        let keys: Vec<&str> = mapping.keys().filter_map(|k| k.as_str()).collect();
+        let schema_keys: Vec<&str> = SCHEMA.iter().map(|(key, _)| *key).collect();
+        assert_eq!(keys, schema_keys);
+        assert_eq!(mapping["noteType"], "evergreen");
+    }
+
+    #[test]
+    fn mdx_bodies_are_escaped_outside_of_code() {
+        let body = "Set {width} when a < b.\n\
+            See <https://docs.astro.build> and [Joints](</garden/dovetail joint/>).\n\
+            <!-- TODO: photos -->\n\
+            Already \\{escaped\\}, `let x = {a: 1} < 2;` stays.\n\
+            ```js\n\
+            const y = {b: <div/>};\n\
+            ```\n";
+        assert_eq!(
+            Astro { mdx: true }.body(body.into()),
+            "Set \\{width\\} when a \\< b.\n\
+            See [https://docs.astro.build](https://docs.astro.build) and [Joints](</garden/dovetail joint/>).\n\
+            {/* TODO: photos */}\n\
+            Already \\{escaped\\}, `let x = {a: 1} < 2;` stays.\n\
+            ```js\n\
+            const y = {b: <div/>};\n\
+            ```\n"
+        );
+        assert_eq!(Astro { mdx: false }.body(body.into()), body);
+    }
+}
@@ -0,0 +1,376 @@
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    astro_files::Astro,
+    craft_files::{add_ext, strip_ext, CraftDocs, FileData, MD_EXT},
+    hugo_files::Hugo,
+    mdbook_files::MdBook,
+    zola_files::Zola,
+};
+
+pub(crate) const DIR_EMOJI: char = '🌳';
+
+/// Backend is the static site generator the notes are written for
+///
+/// It decides the parts of the output which differ between generators: the format of internal
+/// links, the frontmatter, the file marking a directory as a section and where a note with
+/// assets is written (its page bundle).
+pub trait Backend: Send + Sync + fmt::Debug {
+    /// Example: "zola"
+    fn name(&self) -> &str;
+
+    /// The directory of the site the pages live in, the path of OUTPUT_DIR inside it is the mount
+    /// path of internal links (see ZolaFiles::mount_in)
+    fn content_dir(&self) -> &Path {
+        Path::new("content")
+    }
+
+    /// The path a note is written to relative to OUTPUT_DIR, given its slugified path without the
+    /// extension
+    ///
+    /// By default a note with assets is a page bundle, a directory holding the note as `index.md`
+    /// next to its assets.
+    ///
+    /// Example:
+    ///     "woodworking/dovetail-joint", with assets    -> "woodworking/dovetail-joint/index.md"
+    ///     "woodworking/hand-tools",     without assets -> "woodworking/hand-tools.md"
+    fn page_path(&self, page_slug: &Path, has_assets: bool) -> PathBuf {
+        match has_assets {
+            true => page_slug.join("index.md"),
+            false => add_ext(page_slug, MD_EXT),
+        }
+    }
+
+    /// The URL a note is published at, `mount` is the slugified path of OUTPUT_DIR inside the
+    /// site's content directory
+    ///
+    /// Example:
+    ///     "woodworking/dovetail-joint/index.md" -> "/garden/woodworking/dovetail-joint/"
+    ///     "woodworking/hand-tools.md"           -> "/garden/woodworking/hand-tools/"
+    fn page_url(&self, mount: &Path, note: &FileData) -> String {
+        let mut page = note.path_slug.with_extension("");
+        let section_index = self
+            .section_index_name()
+            .map(|name| strip_ext(Path::new(name), MD_EXT));
+        if page
+            .file_name()
+            .is_some_and(|name| name == "index" || section_index.is_some_and(|i| i == name))
+        {
+            page.pop();
+        }
+        format!("/{}/", mount.join(page).display())
+    }
+
+    /// A markdown link from the note `from` to the note `to`, `text` is the link's text and
+    /// `header` is the slugified anchor including the '#'
+    ///
+    /// `mount` is the path of OUTPUT_DIR inside the site's content directory, see
+    /// ZolaFiles::mount
+    fn link(
+        &self,
+        mount: &str,
+        from: &FileData,
+        to: &FileData,
+        text: &str,
+        header: Option<&str>,
+    ) -> String;
+
+    /// The frontmatter block written at the top of a note, including its delimiters
+    fn frontmatter(&self, note: &FileData) -> String;
+
+    /// The body of a note once every transform has run, a site which reads another dialect of
+    /// markdown escapes what it would misread here
+    fn body(&self, body: String) -> String {
+        body
+    }
+
+    /// The name of the file which turns a directory into a section, None when the site has no
+    /// such file
+    fn section_index_name(&self) -> Option<&str> {
+        Some("_index.md")
+    }
+
+    /// The contents of a section's index file, `root` is true for the index of OUTPUT_DIR itself
+    fn section_index(&self, title: &str, root: bool) -> String;
+
+    /// Write every note, its assets and the section index files into OUTPUT_DIR
+    fn write_files(&self, craft_docs: &CraftDocs, output_dir: &Path) -> anyhow::Result<Written> {
+        write_files(self, craft_docs, output_dir)
+    }
+}
+
+/// The backends built into the CLI
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[non_exhaustive]
+pub enum Target {
+    #[default]
+    Zola,
+    /// `_index.md` sections, `{{< ref >}}` links, leaf and branch bundles
+    Hugo,
+    /// A book's `src/` directory with a generated `SUMMARY.md` and relative links
+    #[value(name = "mdbook")]
+    MdBook,
+    /// An Astro content collection of `.md` entries with a Zod schema and a JSON manifest
+    Astro,
+    /// Astro with `.mdx` entries
+    AstroMdx,
+}
+
+impl Target {
+    pub fn backend(self) -> Arc<dyn Backend> {
+        match self {
+            Self::Zola => Arc::new(Zola),
+            Self::Hugo => Arc::new(Hugo),
+            Self::MdBook => Arc::new(MdBook),
+            Self::Astro => Arc::new(Astro { mdx: false }),
+            Self::AstroMdx => Arc::new(Astro { mdx: true }),
+        }
+    }
+}
+
+/// The files a backend wrote, relative to OUTPUT_DIR and sorted
+///
+/// Example: "woodworking/dovetail-joint/index.md"
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Written {
+    pub pages: Vec<PathBuf>,
+    /// The section index files, and files describing the whole site such as mdBook's
+    /// `SUMMARY.md`
+    pub sections: Vec<PathBuf>,
+    pub assets: Vec<PathBuf>,
+}
+
+/// The default Backend::write_files, shared by every backend which writes a directory of pages
+/// with a section index file in every directory
+pub fn write_files(
+    backend: &(impl Backend + ?Sized),
+    craft_docs: &CraftDocs,
+    output_dir: &Path,
+) -> anyhow::Result<Written> {
+    let mut written = Written::default();
+    // The section index files which are notes of their own, see FileData::section
+    let section_notes: HashSet<&Path> = craft_docs
+        .files
+        .values()
+        .filter(|f| f.section && backend.section_index_name().is_some())
+        .map(|f| f.path_slug.as_path())
+        .collect();
+    // Notes are written in parallel, in the order of their paths so the first error is the same
+    // on every run
+    let mut notes: Vec<&FileData> = craft_docs.files.values().collect();
+    notes.sort_by(|a, b| a.path_slug.cmp(&b.path_slug));
+    let results: Vec<anyhow::Result<Vec<PathBuf>>> = notes
+        .par_iter()
+        .map(|file_data| write_note(craft_docs, file_data, output_dir))
+        .collect();
+    for (file_data, assets) in notes.iter().zip(results) {
+        written.assets.extend(assets?);
+        match section_notes.contains(file_data.path_slug.as_path()) {
+            true => written.sections.push(file_data.path_slug.clone()),
+            false => written.pages.push(file_data.path_slug.clone()),
+        }
+    }
+
+    // The section index files are generated once every note is written, one note at a time as
+    // the first note of a directory creates its index
+    for file_data in notes {
+        // If this file is NOT `index` file_name
+        // Then we should create *one* and only *one* section index file
+        // for the parent dir to make it a section
+        if is_bundle(&file_data.path_slug) {
+            continue;
+        }
+        let Some(section_index_name) = backend.section_index_name() else {
+            continue;
+        };
+
+        let mut parent_dir_path = file_data.path_slug.clone();
+        // Remove the file name to get the slugified path to the directory
+        parent_dir_path.pop();
+        // A section's index note is inside its own directory
+        if section_notes.contains(file_data.path_slug.as_path()) {
+            parent_dir_path.pop();
+        }
+        // The index of OUTPUT_DIR itself is written below
+        if parent_dir_path.as_os_str().is_empty() {
+            continue;
+        }
+        // Append "_index.md" as a new file path
+        parent_dir_path.push(section_index_name);
+        if section_notes.contains(parent_dir_path.as_path()) {
+            continue;
+        }
+        let section_file_path = output_dir.join(&parent_dir_path);
+        // Does this file already exist (we've done this before?)
+        let exists = section_file_path.try_exists().with_context(|| {
+            format!(
+                "Attempted to check if '{}' existed on the file system but recieved an error",
+                section_file_path.display()
+            )
+        })?;
+        if exists {
+            continue;
+        }
+        // Create this file with the desired contents
+        let mut parent_dir_title = file_data.path_rel.clone();
+        // Remove the unslugified file name
+        parent_dir_title.pop();
+        // Get the name of the parent directory
+        let parent_dir_title = parent_dir_title.file_name().unwrap().to_str().unwrap();
+        let section_content =
+            backend.section_index(&format!("{DIR_EMOJI} {parent_dir_title}"), false);
+
+        // Write the file
+        fs::write(&section_file_path, section_content).with_context(|| {
+            format!(
+                "Failed to write a section index file at path = {}",
+                section_file_path.display()
+            )
+        })?;
+        written.sections.push(parent_dir_path);
+    }
+    // SPECIAL CASE: The notes which are immediate children of OUTPUT_DIR skipped the loop above,
+    // and so does every folder from the top level directory.
+    //
+    // We will need to generate a top level section index to mark the top level directory as a
+    // section.
+    //
+    // Since I am lazy, I am doing this as a manual special cased step.
+    let Some(section_index_name) = backend.section_index_name() else {
+        written.pages.sort();
+        written.assets.sort();
+        return Ok(written);
+    };
+    let tld_section_index = PathBuf::from(section_index_name);
+    let tld_section_index_path = output_dir.join(&tld_section_index);
+    let section_content = backend.section_index(&format!("{DIR_EMOJI} Garden"), true);
+
+    // Write the file
+    fs::write(&tld_section_index_path, section_content).with_context(|| {
+        format!(
+            "Failed to write a section index file at path = {}",
+            tld_section_index_path.display()
+        )
+    })?;
+    written.sections.push(tld_section_index);
+
+    written.pages.sort();
+    written.sections.sort();
+    written.assets.sort();
+    Ok(written)
+}
+
+/// Write a single note and copy its assets next to it, returns the paths of the assets relative
+/// to OUTPUT_DIR
+fn write_note(
+    craft_docs: &CraftDocs,
+    file_data: &FileData,
+    output_dir: &Path,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let output_path = output_dir.join(&file_data.path_slug);
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&output_path, &file_data.contents)?;
+
+    // If this file has associated assets, write them relative to the file (index.md)
+    let Some(assets) = &file_data.assets else {
+        return Ok(Vec::new());
+    };
+    let mut sibling_file_path_slug = output_path.clone();
+    sibling_file_path_slug.pop();
+    let abs_asset_dir = file_data
+        .assets_dir
+        .as_ref()
+        .expect("There to be an asset_dir on any file_data which also has Some(Vec<Assets>)");
+    // Large images and videos are copied in parallel as well
+    let results: Vec<anyhow::Result<PathBuf>> = assets
+        .par_iter()
+        .map(|asset| {
+            let origin_asset_path = abs_asset_dir.join(&asset.path_rel);
+            let destination_asset_path = sibling_file_path_slug.join(&asset.path_slug);
+            // Assets may live in a sub directory of the '.assets' directory
+            if let Some(parent) = destination_asset_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            craft_docs
+                .copy_asset(&origin_asset_path, &destination_asset_path)
+                .with_context(|| {
+                    format!(
+                        "Failed to copy asset from = {} to = {}",
+                        origin_asset_path.display(),
+                        destination_asset_path.display()
+                    )
+                })?;
+            Ok(destination_asset_path
+                .strip_prefix(output_dir)?
+                .to_path_buf())
+        })
+        .collect();
+    results.into_iter().collect()
+}
+
+/// The path a note is written to relative to OUTPUT_DIR, given its slugified path without the
+/// extension
+///
+/// The index note of a section is written as the section's index file when the backend has one,
+/// otherwise as a page (see Backend::page_path).
+///
+/// Example:
+///     "woodworking", section -> "woodworking/_index.md"
+pub(crate) fn note_path(
+    backend: &(impl Backend + ?Sized),
+    page_slug: &Path,
+    note: &FileData,
+) -> PathBuf {
+    match backend.section_index_name() {
+        Some(index) if note.section => page_slug.join(index),
+        _ => backend.page_path(page_slug, note.assets_dir.is_some()),
+    }
+}
+
+/// Is the note at `path_slug` the `index` of a page bundle (see Backend::page_path)
+pub(crate) fn is_bundle(path_slug: &Path) -> bool {
+    path_slug.file_stem().is_some_and(|stem| stem == "index")
+}
+
+/// A markdown link destination, wrapped in <> when it contains whitespace
+///
+/// With the "safe" and "off" slug strategies the path may contain spaces which are only allowed
+/// in a link destination when it is wrapped in <>.
+pub(crate) fn link_destination(destination: &str) -> Cow<'_, str> {
+    match destination.contains(char::is_whitespace) {
+        true => Cow::Owned(format!("<{destination}>")),
+        false => Cow::Borrowed(destination),
+    }
+}
+
+/// The `aliases:` list of a YAML frontmatter, empty when there are no aliases
+///
+/// The aliases come from the state file, which may have been edited by hand, so they are quoted
+/// by serde_yaml rather than pasted into the frontmatter.
+pub(crate) fn yaml_aliases(note: &FileData) -> String {
+    #[derive(Serialize)]
+    struct Aliases<'a> {
+        aliases: &'a [String],
+    }
+
+    match note.aliases.is_empty() {
+        true => String::new(),
+        false => serde_yaml::to_string(&Aliases {
+            aliases: &note.aliases,
+        })
+        .expect("a list of strings is valid YAML"),
+    }
+}
@@ -1,85 +1,169 @@
 use anyhow::{bail, Context};
-use regex::{Captures, Match, Regex};
+use rayon::prelude::*;
+use regex::Regex;
+use sha2::{Digest, Sha256};
 use std::{
-    collections::{HashMap, HashSet},
-    ffi::OsStr,
-    fs::read_to_string,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs::{self, read_to_string},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 use time::{format_description::well_known::Rfc3339, OffsetDateTime};
-use time::{macros::format_description, Date};
 use unicode_segmentation::UnicodeSegmentation;
 
-use slug::slugify;
-use walkdir::{DirEntry, WalkDir};
+use walkdir::WalkDir;
+
+use crate::{
+    archive::{Archive, ZIP_EXT},
+    backend::{note_path, Backend, Target},
+    dates::{self, DateSource, Dates},
+    filter::NoteFilter,
+    frontmatter::Frontmatter,
+    reader::{is_visible, normalize, InputFormat, Reader, Scan},
+    slugs::{Collision, CollisionStrategy, SlugStrategy},
+    state::{self, NoteState, State},
+    transforms::{code_ranges, Code, DiagnosticFormat, Diagnostics, Pipeline, Span, RE_ASSET_LINK},
+};
 
 lazy_static! {
     static ref RE_FIRST_H1: Regex = Regex::new(r"^\#(.*)\n").unwrap();
-    // Names the capture group "link_name"
-    //
-    // I noticed that because I am using regular expressions on the note bodies, I accidentially
-    // matched on some example code inside of a code-fence
-    //
-    // Example:
-    // ```purescript
-    // list = [[1,2,3], [4,5], [6]]
-    // ```
-    //
-    // I realized that the wiki links regular expression should say
-    // "match on all character inside two brackets, such that every character is itself not a bracket"
+    // A markdown link to a Craft block, only as wide as the link so each one is reported on its own
     //
-    // However that does not exclude this code snippet
-    // Example:
-    // ```rust
-    // [[ 1, 2, 3, 4, 5]]
-    //
-    // Basically if I come back to this program because it panicked for some reason, this may be a
-    // reason why... yippie for Regexs!
-    //```
-    static ref RE_WIKI_LINK: Regex = Regex::new(r"\[\[(?<link_name>[^\[\]]+?)\]\]").unwrap();
-    static ref RE_UUID_V4: Regex = Regex::new(
-        r"\#\^[0-9A-Za-z]{8}-[0-9A-Za-z]{4}-4[0-9A-Za-z]{3}-[89ABab][0-9A-Za-z]{3}-[0-9A-Za-z]{12}"
-    )
-    .unwrap();
-    // Names the capture group "header" and "link_name"
-    static ref RE_HEADER_ANCHOR: Regex = Regex::new(r"(?<link_name>.+)(\#(?<header>.+))").unwrap();
-    // Names the capture group "desc" for the date string,
-    // "day_url" for everything including day://,
-    // and "date" for the actual yyyy.mm.dd
-    static ref RE_DAY_LINK: Regex =
-        Regex::new(r"\[(?<desc>.*)\]\((?<day_url>day:\/\/(?<date>\d{4}\.\d{2}\.\d{2}))\)").unwrap();
-    static ref RE_IMG_ASSET_LINK: Regex =
-        Regex::new(r"\!\[(?<name>.*)?\]\((.*\.assets\/)(?<file_name>.*)\)").unwrap();
-    static ref RE_CRAFTDOCS_LINK: Regex = Regex::new(r"\[.*\]\((craftdocs:\/\/open.*)\)").unwrap();
-    static ref RE_CODE_BLOCK_OTHER: Regex =  Regex::new(r"```other").unwrap();
+    // Example: [Plan](craftdocs://open?blockId=1&spaceId=2)
+    static ref RE_CRAFTDOCS_LINK: Regex =
+        Regex::new(r"\[[^\[\]]*\]\((craftdocs://open[^()\s]*)\)").unwrap();
+    // A #private or #draft tag anywhere in a note's body outside of code, see without_code.
+    // Headings need a space after the '#' so they are never matched, and the tag has to end
+    // there so #private-beta is another tag.
+    static ref RE_PRIVATE_TAG: Regex = Regex::new(r"(?m)(^|\s)#private(?:\s|$)").unwrap();
+    static ref RE_DRAFT_TAG: Regex = Regex::new(r"(?m)(^|\s)#draft(?:\s|$)").unwrap();
 }
 
 // =============
 
 const ASSETS_DIR_EXT: &str = "assets";
-const MD_EXT: &str = "md";
-const BIN_EXT: &str = "bin";
-const PNG_EXT: &str = "png";
+pub(crate) const MD_EXT: &str = "md";
 const UNIC_EVERGREEN: char = '🌲';
 const UNIC_POTTED: char = '🪴';
 const UNIC_SEEDLING: char = '🌱';
+const UNIC_PRIVATE: char = '🔒';
+const UNIC_DRAFT: char = '🚧';
 
 // =============
 
+/// The body with its code blanked out so tags inside of it are not matched. Code is replaced
+/// rather than removed so the text around it is not joined, only line breaks are kept.
+fn without_code(body: &str) -> String {
+    let mut text = String::with_capacity(body.len());
+    let mut last = 0;
+    for range in code_ranges(body) {
+        text.push_str(&body[last..range.start]);
+        text.extend(body[range.clone()].chars().map(|c| match c {
+            '\n' => '\n',
+            _ => '`',
+        }));
+        last = range.end;
+    }
+    text.push_str(&body[last..]);
+    text
+}
+
 fn strip_input_dir(p: &Path, input_dir: &PathBuf) -> anyhow::Result<PathBuf> {
     Ok(p.strip_prefix(input_dir)?.to_path_buf())
 }
-fn create_input_path(input_dir: &Path, p: &PathBuf) -> PathBuf {
-    input_dir.join(p)
-}
 
-fn slugify_path(p: &Path) -> PathBuf {
+fn slugify_path(p: &Path, strategy: SlugStrategy) -> PathBuf {
     p.components()
         .map(|x| x.as_os_str().to_str().unwrap())
-        .map(slugify)
+        .map(|x| strategy.slugify_path(x))
         .collect::<PathBuf>()
 }
 
+/// Remove the extension `ext` from the file name of `p` only if it is the real extension
+///
+/// Unlike `Path::set_extension("")` this never eats a dotted part of a name which has no such
+/// extension
+///
+/// Example:
+///     "Releases/Rust 1.70 Release.md" -> "Releases/Rust 1.70 Release"
+///     "Plans/v2.0 Plan.assets"        -> "Plans/v2.0 Plan"
+///     "Plans/v2.0 Plan"               -> "Plans/v2.0 Plan"
+pub(crate) fn strip_ext(p: &Path, ext: &str) -> PathBuf {
+    match p.extension() {
+        Some(e) if e == ext => p.with_extension(""),
+        _ => p.to_path_buf(),
+    }
+}
+
+/// Append `.{ext}` to the file name of `p`, keeping any dots already in the name
+///
+/// Example:
+///     "releases/rust-1.70-release" -> "releases/rust-1.70-release.md"
+pub(crate) fn add_ext(p: &Path, ext: &str) -> PathBuf {
+    let mut s = p.as_os_str().to_os_string();
+    s.push(".");
+    s.push(ext);
+    PathBuf::from(s)
+}
+
+/// Returns the closest ancestor of `p` which is a '.assets' directory (if any)
+///
+/// Example:
+///     "Woodworking/Dovetail Joint.assets/drawings/plan.pdf"
+///     "Woodworking/Dovetail Joint.assets"
+fn assets_ancestor(p: &Path) -> Option<&Path> {
+    p.ancestors()
+        .skip(1)
+        .find(|a| a.extension().is_some_and(|ext| ext == ASSETS_DIR_EXT))
+}
+
+/// Slugify a single path component the same way set_paths does
+///
+/// Example:
+///     "🚀 Rockets" -> "rockets"
+fn slugify_component(name: &str, strategy: SlugStrategy) -> String {
+    slugify_path(&strip_emoji(Path::new(name)), strategy)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Slugify every component of an asset path while keeping the file's extension intact
+///
+/// Example:
+///     "Drawings/🪚 Image 1.JPEG" -> "drawings/image-1.jpeg"
+///     "🪚.png"                   -> "asset-cda64cbc.png"
+fn slugify_asset_path(p: &Path, strategy: SlugStrategy) -> PathBuf {
+    let mut path_slug = p
+        .parent()
+        .map(|parent| slugify_path(&strip_emoji(parent), strategy))
+        .unwrap_or_default();
+    let stem = p.file_stem().map(Path::new).unwrap_or(Path::new(""));
+    let mut file_name = slugify_path(&strip_emoji(stem), strategy);
+    // A file name which was nothing but emoji or symbols leaves an empty stem behind. It is named
+    // after a short hash of the original name so such assets of the same note stay apart.
+    if file_name.as_os_str().is_empty() {
+        let digest = Sha256::digest(p.file_name().unwrap_or_default().as_encoded_bytes());
+        let hash: String = digest[..4].iter().map(|b| format!("{b:02x}")).collect();
+        file_name = PathBuf::from(format!("asset-{hash}"));
+    }
+    if let Some(ext) = p.extension() {
+        file_name = add_ext(&file_name, &ext.to_string_lossy().to_lowercase());
+    }
+    path_slug.push(file_name);
+    path_slug
+}
+
+/// Craft exports a preview image next to every file it can not show, named after the file
+///
+/// Example: "blob.bin" -> "blob_bin_preview.png"
+fn is_preview(asset: &Asset, assets: &[Asset]) -> bool {
+    assets.iter().any(|a| {
+        let name = a.path_rel.file_name().unwrap_or_default().to_string_lossy();
+        let preview = format!("{}_preview.png", name.replace('.', "_"));
+        a.path_rel.with_file_name(preview) == asset.path_rel
+    })
+}
+
 // https://stackoverflow.com/a/76909909
 fn strip_emoji(p: &Path) -> PathBuf {
     p.components()
@@ -94,16 +178,18 @@ fn strip_emoji(p: &Path) -> PathBuf {
 
 // =============
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Hash, PartialEq, Eq)]
 pub enum NoteType {
     Evergreen,
     Potted,
     Seedling,
+    #[default]
     None,
 }
 
 impl NoteType {
-    fn to_weight(&self) -> char {
+    /// The `weight` of the note's page, lower values are listed first
+    pub fn to_weight(&self) -> char {
         match self {
             Self::Evergreen => '1',
             Self::Potted => '2',
@@ -112,7 +198,7 @@ impl NoteType {
         }
     }
 
-    fn as_emoji(&self) -> char {
+    pub fn as_emoji(&self) -> char {
         match self {
             Self::Evergreen => UNIC_EVERGREEN,
             Self::Potted => UNIC_POTTED,
@@ -122,12 +208,6 @@ impl NoteType {
     }
 }
 
-impl Default for NoteType {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
 impl From<usize> for NoteType {
     fn from(value: usize) -> Self {
         // Lower values have high priority in Zola
@@ -156,6 +236,7 @@ impl From<&str> for NoteType {
 }
 
 #[derive(Default, Clone, Debug, Hash, PartialEq, Eq)]
+#[non_exhaustive]
 pub struct FileData {
     /// Evergreen | Potted | Seedling | None
     pub note_type: NoteType,
@@ -183,32 +264,77 @@ pub struct FileData {
     /// Importantly, any asset will be co-located in the same directory so the markdown link will
     /// not need to have any paths, just the file name.
     ///
-    /// This means that all entries in this vector will be relative to path_slug
+    /// This means that all entries in this vector will be relative to path_slug. Every file inside
+    /// the '.assets' directory is included (images, PDFs, audio, video, zip, ...) and keeps any
+    /// sub directory it was exported with.
     ///
     /// Example:
     /// path_slug = woodworking/joinery/index.md
-    /// assets =
-    pub assets: Option<Vec<PathBuf>>,
+    /// assets = ["Image 1.jpeg", "plan.pdf", "drawings/side.png"]
+    pub assets: Option<Vec<Asset>>,
     ///  Asset Dir is the path to the containing directory of the markdown file's image assets
     ///  It is the relative path, including the ".assets" name on the directory
     ///
     ///  Example:
     ///  "Woodworking/Dovetail Joing.assets""
     pub assets_dir: Option<PathBuf>,
+    /// The note as read from the input, in Craft's flavour of Markdown (see Reader)
+    pub body: String,
     // Contents is the file contents after we have processed it (replacements)
     pub contents: String,
     /// Craft will set this for us as its internal time of when the file was created
     pub created_at: String,
     /// Craft will set this for us as its internal time of when the file was modified
     pub modified_at: String,
+    /// URLs this note was published at in previous runs, written as Zola `aliases` so they
+    /// redirect to the current URL
+    ///
+    /// Example: ["/garden/woodworking/dovetails/"]
+    pub aliases: Vec<String>,
+    /// Drafts are written with `draft: true` so Zola only builds them with `--drafts`
+    pub draft: bool,
+    /// The name of the Craft space this note was exported from
+    pub space: String,
+    /// The note is the index of the section at its path_rel, such as a Notion page with sub pages
+    ///
+    /// It is written as the section's index file with the backend's section frontmatter, see
+    /// backend::note_path
+    pub section: bool,
 }
 
 impl FileData {
-    pub fn set_paths(&mut self, input_dir: &PathBuf) -> anyhow::Result<()> {
-        let mut path_rel = strip_input_dir(&self.path_full, input_dir)?;
+    /// Find the asset whose path (relative to the '.assets' directory) matches `path_rel`
+    ///
+    /// The caller is expected to have percent-decoded the path from the markdown link first
+    pub fn find_asset(&self, path_rel: &Path) -> Option<&Asset> {
+        self.assets
+            .as_ref()
+            .and_then(|assets| assets.iter().find(|a| a.path_rel == path_rel))
+    }
+
+    /// Set path_rel and path_slug from path_full
+    ///
+    /// `prefix` is put in front of path_rel, it is the name of the note's space when more than one
+    /// space is converted (see Space)
+    pub fn set_paths(
+        &mut self,
+        input_dir: &PathBuf,
+        prefix: &Path,
+        strategy: SlugStrategy,
+    ) -> anyhow::Result<()> {
+        let path_rel = strip_input_dir(&self.path_full, input_dir)?;
         // Drop '.md' from the key, it is implied with files
         // Example : Woodworking/Joinery/Dovetail Joint
-        path_rel.set_extension("");
+        self.set_key(&strip_ext(&path_rel, MD_EXT), prefix, strategy);
+        Ok(())
+    }
+
+    /// Set path_rel and path_slug from the note's path inside its space without the extension,
+    /// for readers whose file names are not the names of the notes (see Reader)
+    ///
+    /// Example: "pages/Woodworking___Dovetail Joint.md" -> "Woodworking/Dovetail Joint"
+    pub fn set_key(&mut self, key: &Path, prefix: &Path, strategy: SlugStrategy) {
+        let path_rel = prefix.join(key);
 
         // Remove all emoji from the path_slug.
         // Otherwise they are convereted into their shortcode representation by slugify
@@ -218,16 +344,63 @@ impl FileData {
         let mut path_slug = strip_emoji(&path_rel);
 
         // Slugify the path with no eomji hooray
-        path_slug = slugify_path(&path_slug);
+        path_slug = slugify_path(&path_slug, strategy);
 
         // Add the extension back after slugification
         // This is because Zola wants a link in this format
         // [Page Name](@/garden/page-name.md)
-        path_slug.set_extension(MD_EXT);
+        path_slug = add_ext(&path_slug, MD_EXT);
 
         self.path_slug = path_slug;
         self.path_rel = path_rel;
-        Ok(())
+    }
+}
+
+#[derive(Default, Clone, Debug, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Asset {
+    /// Path is the relative path to the '.assets' directory, exactly as it is on disk (decoded)
+    ///
+    /// Example: `Woodworking/Dovetail Joint.assets/` + `drawings/Image 1.jpeg`
+    ///                                                 ^_____________________^____path
+    pub path_rel: PathBuf,
+    /// The path the asset is copied to, relative to the co-located note (index.md)
+    ///
+    /// This is the same as path_rel unless asset names are slugified
+    ///
+    /// Example:
+    ///     "drawings/Image 1.jpeg"
+    ///     "drawings/image-1.jpeg"
+    pub path_slug: PathBuf,
+}
+
+impl Asset {
+    /// Slugify the asset's name with `slugify_name` or keep it as is with None
+    pub fn new(path_rel: PathBuf, slugify_name: Option<SlugStrategy>) -> Self {
+        let path_slug = match slugify_name {
+            Some(strategy) => slugify_asset_path(&path_rel, strategy),
+            None => path_rel.clone(),
+        };
+        Self {
+            path_rel,
+            path_slug,
+        }
+    }
+
+    /// The path_slug formatted for use as a markdown link destination
+    ///
+    /// Each component is percent-encoded since asset names may contain spaces or other
+    /// characters which are not allowed inside a markdown link
+    ///
+    /// Example:
+    ///     "drawings/Image 1.jpeg"
+    ///     "drawings/Image%201.jpeg"
+    pub fn link(&self) -> String {
+        self.path_slug
+            .components()
+            .map(|x| urlencoding::encode(&x.as_os_str().to_string_lossy()).into_owned())
+            .collect::<Vec<_>>()
+            .join("/")
     }
 }
 
@@ -235,9 +408,26 @@ impl TryFrom<PathBuf> for FileData {
     type Error = anyhow::Error;
 
     fn try_from(path: PathBuf) -> Result<Self, Self::Error> {
+        // Many Linux file systems do not record a creation time, use the modification time instead
+        // of giving up on the whole run
+        let metadata = path.metadata()?;
+        let mtime = metadata.modified()?;
+        let ctime = metadata.created().unwrap_or(mtime);
+        Self::with_dates(path, ctime.into(), mtime.into())
+    }
+}
+
+impl FileData {
+    /// A note at `path` created and modified at the given times, for notes which are not read
+    /// from a file of their own such as the entries of an archive
+    pub(crate) fn with_dates(
+        path: PathBuf,
+        ctime: OffsetDateTime,
+        mtime: OffsetDateTime,
+    ) -> anyhow::Result<Self> {
         let path_full = path.clone();
         // Drop extension (.md)
-        let name = path.with_extension("");
+        let name = strip_ext(&path, MD_EXT);
         let name = name.file_name().with_context(|| {
             format!(
                 "Received a path without a valid file name: path={}",
@@ -250,9 +440,6 @@ impl TryFrom<PathBuf> for FileData {
 
         let note_type = NoteType::from(name);
 
-        let metadata = path_full.metadata()?;
-        let ctime: OffsetDateTime = metadata.created()?.into();
-        let mtime: OffsetDateTime = metadata.modified()?.into();
         let created_at = ctime.format(&Rfc3339)?;
         let modified_at = mtime.format(&Rfc3339)?;
 
@@ -264,9 +451,14 @@ impl TryFrom<PathBuf> for FileData {
             path_slug: PathBuf::default(),
             assets: None,
             assets_dir: None,
+            body: String::default(),
             contents: String::default(),
             created_at,
             modified_at,
+            aliases: Vec::new(),
+            draft: false,
+            space: String::default(),
+            section: false,
         })
     }
 }
@@ -280,12 +472,479 @@ pub struct Directory {
     pub name: String,
 }
 
+/// Options which change how the Craft export is converted
+///
+/// Built from `Options::default()` with the builder methods of the same name as the fields.
+///
+/// Example:
+/// ```
+/// use craft_zola::{Options, SlugStrategy};
+///
+/// let options = Options::default()
+///     .slugify_paths(SlugStrategy::Safe)
+///     .include("Engineering/")
+///     .include("Reading/*.md");
+/// assert_eq!(options.include.len(), 2);
+/// ```
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Options {
+    /// Slugify the names of asset files when they are copied next to their note and rewrite the
+    /// links accordingly
+    ///
+    /// Example: "Image 1.jpeg" -> "image-1.jpeg"
+    pub slugify_assets: bool,
+    /// Do not copy assets which are never linked to from their note's body
+    pub prune_unreferenced_assets: bool,
+    /// How to resolve pages, sections and assets which end up with the same slug
+    pub on_collision: CollisionStrategy,
+    /// How file and directory names are turned into paths, should match Zola's `slugify.paths`
+    pub slugify_paths: SlugStrategy,
+    /// How the `#header` of a [[wiki link]] is turned into an anchor, should match Zola's
+    /// `slugify.anchors`
+    pub slugify_anchors: SlugStrategy,
+    /// Where the `date` and `updated` of a note come from, the first source which knows a date
+    /// wins
+    pub date_sources: Vec<DateSource>,
+    /// Gitignore-style patterns of notes to convert, when empty every note is converted
+    pub include: Vec<String>,
+    /// Gitignore-style patterns of notes to leave out, on top of the patterns in the export's
+    /// `.craftzolaignore`
+    pub exclude: Vec<String>,
+    /// The transforms every note's body goes through
+    pub transforms: Pipeline,
+    /// The static site generator the notes are written for
+    pub backend: Arc<dyn Backend>,
+    /// The format of the spaces being converted
+    pub reader: Arc<dyn Reader>,
+    /// How warnings and errors about the notes are printed
+    pub diagnostic_format: DiagnosticFormat,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            slugify_assets: false,
+            prune_unreferenced_assets: false,
+            on_collision: CollisionStrategy::default(),
+            slugify_paths: SlugStrategy::default(),
+            slugify_anchors: SlugStrategy::default(),
+            date_sources: DateSource::DEFAULT_ORDER.to_vec(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            transforms: Pipeline::default(),
+            backend: Target::default().backend(),
+            reader: InputFormat::default().reader(),
+            diagnostic_format: DiagnosticFormat::default(),
+        }
+    }
+}
+
+impl Options {
+    pub fn slugify_assets(mut self, slugify_assets: bool) -> Self {
+        self.slugify_assets = slugify_assets;
+        self
+    }
+
+    pub fn prune_unreferenced_assets(mut self, prune_unreferenced_assets: bool) -> Self {
+        self.prune_unreferenced_assets = prune_unreferenced_assets;
+        self
+    }
+
+    pub fn on_collision(mut self, on_collision: CollisionStrategy) -> Self {
+        self.on_collision = on_collision;
+        self
+    }
+
+    pub fn slugify_paths(mut self, slugify_paths: SlugStrategy) -> Self {
+        self.slugify_paths = slugify_paths;
+        self
+    }
+
+    pub fn slugify_anchors(mut self, slugify_anchors: SlugStrategy) -> Self {
+        self.slugify_anchors = slugify_anchors;
+        self
+    }
+
+    pub fn date_sources(mut self, date_sources: Vec<DateSource>) -> Self {
+        self.date_sources = date_sources;
+        self
+    }
+
+    /// Add a pattern of notes to convert
+    pub fn include(mut self, pattern: impl Into<String>) -> Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    /// Add a pattern of notes to leave out
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    pub fn diagnostic_format(mut self, diagnostic_format: DiagnosticFormat) -> Self {
+        self.diagnostic_format = diagnostic_format;
+        self
+    }
+
+    pub fn transforms(mut self, transforms: Pipeline) -> Self {
+        self.transforms = transforms;
+        self
+    }
+
+    pub fn backend(mut self, backend: impl Backend + 'static) -> Self {
+        self.backend = Arc::new(backend);
+        self
+    }
+
+    /// Use one of the built-in backends
+    pub fn target(mut self, target: Target) -> Self {
+        self.backend = target.backend();
+        self
+    }
+
+    pub fn reader(mut self, reader: impl Reader + 'static) -> Self {
+        self.reader = Arc::new(reader);
+        self
+    }
+
+    /// Use one of the built-in readers
+    pub fn input_format(mut self, input_format: InputFormat) -> Self {
+        self.reader = input_format.reader();
+        self
+    }
+}
+
+/// Space is one exported Craft space (or folder)
+///
+/// When more than one space is converted in the same run, every space becomes its own section
+/// named after the space and [[wiki links]] resolve across spaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Space {
+    /// Example: "Engineering"
+    pub name: String,
+    /// The top level directory of the exported space
+    pub input_dir: PathBuf,
+    /// Put in front of the path_rel of every note of this space. Empty when there is only one
+    /// space, otherwise the space's name.
+    ///
+    /// Example: "Engineering" + "Cryptography/TLS"
+    pub prefix: PathBuf,
+}
+
+impl Space {
+    /// A space named after its directory, or its archive without the `.zip` extension
+    pub fn new(input_dir: PathBuf) -> Self {
+        let name = strip_ext(&input_dir, ZIP_EXT);
+        let name = name.file_name().unwrap_or_default();
+        Self::named(name.to_string_lossy().into_owned(), input_dir)
+    }
+
+    pub fn named(name: String, input_dir: PathBuf) -> Self {
+        Self {
+            name,
+            input_dir,
+            prefix: PathBuf::new(),
+        }
+    }
+}
+
+/// Craft's Markdown export of a space, chosen with `--from craft`
+///
+/// Every note is a `.md` file and the files it links to (images, PDFs, ...) live in a directory
+/// next to it with the same name and the `.assets` extension.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Craft;
+
+impl Reader for Craft {
+    fn name(&self) -> &str {
+        "craft"
+    }
+
+    /// Scans the input_dir in two passes.
+    ///
+    /// The first pass collects the notes, the directories, the '.assets' directories and the
+    /// asset files separately. The second pass joins every '.assets' directory and asset file to
+    /// its note. This way the association never depends on the order WalkDir visits entries in.
+    ///
+    /// A space given as a `.zip` file is read from the archive's entries instead, see Archive.
+    fn read(&self, space: &Space, options: &Options) -> anyhow::Result<Scan> {
+        let mut diagnostics = Diagnostics::default();
+        let archive = match Archive::is_archive(&space.input_dir) {
+            true => Some(Arc::new(Archive::open(&space.input_dir, &mut diagnostics)?)),
+            false => None,
+        };
+        let mut scan = CraftScan {
+            space,
+            options,
+            archive,
+            notes: Vec::new(),
+            files: BTreeMap::new(),
+            directories: Vec::new(),
+            diagnostics,
+        };
+        // Asset directories as (path relative to the space, full path)
+        let mut asset_dirs: Vec<(PathBuf, PathBuf)> = Vec::new();
+        let mut asset_files: Vec<PathBuf> = Vec::new();
+        scan.walk(&mut asset_dirs, &mut asset_files)?;
+
+        // The notes are read in parallel, the first error is the one of the first note in walk
+        // order
+        let notes: Vec<anyhow::Result<FileData>> = scan
+            .notes
+            .par_iter()
+            .map(|rel_path| scan.read_note(rel_path))
+            .collect();
+        for file_data in notes {
+            let file_data = file_data?;
+            scan.files.insert(file_data.path_rel.clone(), file_data);
+        }
+
+        // Second pass: every note has been collected, join the assets to their notes
+        for (asset_dir, full_path) in asset_dirs {
+            scan.set_asset_dir(asset_dir, full_path);
+        }
+        for asset_file in asset_files {
+            scan.set_asset_on_file(&asset_file)?;
+        }
+        Ok(Scan {
+            files: scan.files.into_values().collect(),
+            directories: scan.directories,
+            archives: scan.archive.into_iter().collect(),
+            diagnostics: scan.diagnostics,
+        })
+    }
+
+    fn reads_archives(&self) -> bool {
+        true
+    }
+
+    fn keeps_source(&self) -> bool {
+        true
+    }
+}
+
+/// The notes of a single space while Craft::read joins their assets
+struct CraftScan<'a> {
+    space: &'a Space,
+    options: &'a Options,
+    /// The archive of a space given as a `.zip` file
+    archive: Option<Arc<Archive>>,
+    /// The notes found by the first pass relative to the space, read once it is done
+    notes: Vec<PathBuf>,
+    files: BTreeMap<PathBuf, FileData>,
+    directories: Vec<PathBuf>,
+    diagnostics: Diagnostics,
+}
+
+impl CraftScan<'_> {
+    /// The first pass of Craft::read
+    fn walk(
+        &mut self,
+        asset_dirs: &mut Vec<(PathBuf, PathBuf)>,
+        asset_files: &mut Vec<PathBuf>,
+    ) -> anyhow::Result<()> {
+        let space = self.space;
+        if let Some(archive) = self.archive.clone() {
+            for rel_path in archive.directories() {
+                self.visit_dir(rel_path, asset_dirs);
+            }
+            for rel_path in archive.files() {
+                self.visit_file(rel_path.to_path_buf(), asset_files)?;
+            }
+            return Ok(());
+        }
+
+        for entry in WalkDir::new(&space.input_dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(is_visible)
+        {
+            let entry = entry?;
+            let full_path = entry.into_path();
+            if full_path == space.input_dir {
+                continue;
+            }
+            // Make path relative to the input dir
+            let rel_path = strip_input_dir(&full_path, &space.input_dir)?;
+
+            if full_path.is_dir() {
+                self.visit_dir(rel_path, asset_dirs);
+            } else if full_path.is_file() {
+                self.visit_file(rel_path, asset_files)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_dir(&mut self, rel_path: PathBuf, asset_dirs: &mut Vec<(PathBuf, PathBuf)>) {
+        // Sub directories of an '.assets' directory are part of the assets themselves
+        if assets_ancestor(&rel_path).is_some() {
+            return;
+        }
+        let full_path = self.space.input_dir.join(&rel_path);
+        if rel_path
+            .extension()
+            .is_some_and(|ext| ext == ASSETS_DIR_EXT)
+        {
+            asset_dirs.push((self.space.prefix.join(rel_path), full_path));
+            return;
+        }
+        self.directories.push(self.space.prefix.join(rel_path));
+    }
+
+    fn visit_file(
+        &mut self,
+        rel_path: PathBuf,
+        asset_files: &mut Vec<PathBuf>,
+    ) -> anyhow::Result<()> {
+        // Any file living under an '.assets' directory is an asset (images, PDFs, audio,
+        // video, zip, ...). We do not want to use it as a key into our HashMap, it is
+        // joined to its note in the second pass.
+        if assets_ancestor(&rel_path).is_some() {
+            asset_files.push(self.space.prefix.join(rel_path));
+            return Ok(());
+        }
+        let full_path = self.space.input_dir.join(&rel_path);
+        let ext = full_path.extension()
+            .with_context(||
+                format!("Trying to create a FileData entry in HashMap but could not access the file's extension for file = {}", full_path.display())
+            )?;
+        if ext != MD_EXT {
+            self.diagnostics.warning(
+                Code::SkippedFile,
+                &full_path,
+                format!("the file is neither a markdown note nor inside an '.{ASSETS_DIR_EXT}' directory, it is skipped"),
+            );
+            return Ok(());
+        }
+        self.notes.push(rel_path);
+        Ok(())
+    }
+
+    fn read_note(&self, rel_path: &Path) -> anyhow::Result<FileData> {
+        let full_path = self.space.input_dir.join(rel_path);
+        let mut file_data = match &self.archive {
+            Some(archive) => {
+                // Zip only records when an entry was modified
+                let modified = archive.modified(rel_path)?;
+                FileData::with_dates(full_path.clone(), modified, modified)?
+            }
+            None => FileData::try_from(full_path.clone())?,
+        };
+        // Set path_rel, path_slug
+        file_data.set_paths(
+            &self.space.input_dir,
+            &self.space.prefix,
+            self.options.slugify_paths,
+        )?;
+        file_data.body = match &self.archive {
+            Some(archive) => archive.read_to_string(rel_path)?,
+            None => read_to_string(&full_path)
+                .with_context(|| format!("Failed to read file = {}", full_path.display()))?,
+        };
+        Ok(file_data)
+    }
+
+    /// The key of the note which owns the given '.assets' directory
+    ///
+    /// Example:
+    /// Hand Tools Woodworking.assets
+    /// Hand Tools Woodworking
+    fn note_key_for_assets_dir(assets_dir: &Path) -> PathBuf {
+        strip_ext(assets_dir, ASSETS_DIR_EXT)
+    }
+
+    fn set_asset_on_file(&mut self, asset_path: &Path) -> anyhow::Result<()> {
+        // NOTE: We are using the fact that all images/media will live inside a directory
+        // called "{Some Markdown File Name}.assets/{asset_path}.{ext}"
+        // Therefore we can get to the file's name using only the asset's directory.
+        let assets_dir = assets_ancestor(asset_path).with_context(|| {
+            format!(
+                "Expected the asset = {} to live inside an '.{ASSETS_DIR_EXT}' directory",
+                asset_path.display()
+            )
+        })?;
+        let key = Self::note_key_for_assets_dir(assets_dir);
+
+        // An orphaned '.assets' directory has already been reported by set_asset_dir
+        let Some(file) = self.files.get_mut(&key) else {
+            return Ok(());
+        };
+
+        // The asset path relative to its '.assets' directory, this may include sub
+        // directories
+        //
+        // Example:
+        // Hand Tools Woodworking.assets/drawings/plan.pdf
+        // drawings/plan.pdf
+        let file_name_path = asset_path.strip_prefix(assets_dir)?.to_path_buf();
+        let slugify_name = self
+            .options
+            .slugify_assets
+            .then_some(self.options.slugify_paths);
+        let asset = Asset::new(file_name_path, slugify_name);
+
+        // Push the asset onto the file_data's assets vec, or create it if one does not exist
+        match &mut file.assets {
+            Some(a) => a.push(asset),
+            None => file.assets = Some(vec![asset]),
+        };
+        Ok(())
+    }
+
+    fn set_asset_dir(&mut self, rel_path: PathBuf, full_path: PathBuf) {
+        // Find the associated file that matches the name of the asset directory
+        let key = Self::note_key_for_assets_dir(&rel_path);
+        let Some(file) = self.files.get_mut(&key) else {
+            self.diagnostics.warning(
+                Code::OrphanedAssets,
+                &full_path,
+                format!(
+                    "the assets directory has no matching note = {}.{MD_EXT}, its assets will not be copied",
+                    key.display()
+                ),
+            );
+            return;
+        };
+
+        // If this file has an associated assets directory we will have to co-locate the final
+        // markdown file into the same directory, see Backend::page_path
+        //
+        // Example:
+        //  File: "cryptography/aes.md"
+        //  Assets: "cryptography/AES.assets/"
+        //  Final File Path: crypography/aes/index.md
+        //                               ^ will also be the assets dir
+        let page_slug = strip_ext(&file.path_slug, MD_EXT);
+        file.path_slug = self.options.backend.page_path(&page_slug, true);
+
+        // Set this directory as the assets_dir on the matching FileData
+        // Example: {INPUT_DIR}/Woodworking/DoveTail Joing.assets/
+        file.assets_dir = Some(full_path);
+    }
+}
+
+/// A note converted by format_markdown
+struct Formatted {
+    contents: String,
+    /// The note's assets without the unreferenced ones when they are pruned
+    assets: Option<Vec<Asset>>,
+    diagnostics: Diagnostics,
+}
+
 #[derive(Debug)]
 pub struct CraftDocs {
-    /// input_dir is the top level directory of the exported Craft fodler or space
-    input_dir: PathBuf,
-    /// The name of this directory will be used as the new name when imported into Zola
-    input_dir_name: String,
+    /// Spaces are the exported Craft spaces (or folders) being converted
+    spaces: Vec<Space>,
+    /// The path of the OUTPUT directory inside Zola's `content/` directory, used to build the
+    /// internal `@/` links. Empty when OUTPUT is the `content/` directory itself.
+    ///
+    /// Example: "garden" -> [TLS](@/garden/cryptography/tls.md)
+    mount: String,
+    options: Options,
     /// Directories is a unique set of paths to directories within the input_dir.
     ///
     /// This is used when parsing a file's markdown wiki style links to construct the final
@@ -308,202 +967,472 @@ pub struct CraftDocs {
     ///
     /// Note: The key is the file's path WITHOUT the `.md` extension
     pub files: HashMap<PathBuf, FileData>,
+    /// Excluded is the set of keys of the notes which are left out of the site, either because
+    /// they are private or because they match an exclude pattern (see NoteFilter).
+    ///
+    /// [[wiki links]] to them become plain text and their assets are not copied.
+    pub excluded: HashSet<PathBuf>,
+    /// The archives of the spaces given as `.zip` files, their assets are copied out of them
+    archives: Vec<Arc<Archive>>,
+    /// The problems found so far, reported by format_markdown or when a step fails on them
+    pub diagnostics: Diagnostics,
 }
 
 impl CraftDocs {
-    pub fn new(input_dir: PathBuf) -> Self {
-        let input_dir_name = input_dir.file_name().unwrap_or_default();
-        let input_dir_name: String = input_dir_name.to_str().unwrap_or_default().into();
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// The path of OUTPUT_DIR inside the site's content directory, see ZolaFiles::mount
+    pub fn mount(&self) -> &str {
+        &self.mount
+    }
+
+    pub fn new(mut spaces: Vec<Space>, mount: String, options: Options) -> Self {
+        // Every space gets its own section once there is more than one
+        if spaces.len() > 1 {
+            for space in spaces.iter_mut() {
+                space.prefix = PathBuf::from(&space.name);
+            }
+        }
         CraftDocs {
-            input_dir,
-            input_dir_name,
+            spaces,
+            mount,
+            options,
             directories: HashSet::new(),
             files: HashMap::new(),
+            excluded: HashSet::new(),
+            archives: Vec::new(),
+            diagnostics: Diagnostics::default(),
         }
     }
 
+    /// process_files reads every space with `Options::reader` and leaves out the notes which are
+    /// excluded or private
     pub fn process_files(&mut self) -> anyhow::Result<()> {
-        let files_first_cmp = |a: &DirEntry| if a.path().is_dir() { 2 } else { 0 };
-        for entry in WalkDir::new(&self.input_dir).sort_by_key(files_first_cmp) {
-            let entry = entry?;
-            // Make path relative to the input dir
-            let full_path = &entry.into_path();
+        let mut names = HashSet::new();
+        if let Some(space) = self.spaces.iter().find(|s| !names.insert(&s.name)) {
+            bail!(
+                "More than one input is named '{}', give them distinct names with --input NAME=PATH",
+                space.name
+            );
+        }
 
-            if full_path.is_dir() {
-                if *full_path == self.input_dir {
-                    continue;
-                }
-                self.set_directory(full_path.clone())?;
+        for space in &self.spaces {
+            if Archive::is_archive(&space.input_dir) && !self.options.reader.reads_archives() {
+                bail!(
+                    "The input = {} is an archive, which the {} reader can not read. Unzip it first.",
+                    space.input_dir.display(),
+                    self.options.reader.name()
+                );
             }
-
-            if full_path.is_file() {
-                let file_name = full_path.file_name().with_context(|| {
-                    format!("Unable to get file name for path = {}", full_path.display())
+            let filter = NoteFilter::new(
+                &space.input_dir,
+                &self.options.include,
+                &self.options.exclude,
+            )?;
+            let scan = self
+                .options
+                .reader
+                .read(space, &self.options)
+                .with_context(|| {
+                    format!(
+                        "Failed to read the space = {} as {}",
+                        space.input_dir.display(),
+                        self.options.reader.name()
+                    )
                 })?;
-                if file_name == ".DS_Store" {
+            self.directories.extend(scan.directories);
+            self.archives.extend(scan.archives);
+            self.diagnostics.append(scan.diagnostics);
+            for mut file_data in scan.files {
+                // Patterns are matched against the note's path inside its space
+                let rel_path = file_data
+                    .path_rel
+                    .strip_prefix(&space.prefix)
+                    .unwrap_or(&file_data.path_rel);
+                // Excluded notes are handled the same way as private notes, their assets are
+                // never copied
+                if filter.is_excluded(&add_ext(rel_path, MD_EXT)) {
+                    self.excluded.insert(file_data.path_rel);
                     continue;
                 }
-                self.set_file(full_path.clone())?;
+                file_data.space = space.name.clone();
+                self.files.insert(file_data.path_rel.clone(), file_data);
             }
         }
-        Ok(())
+
+        self.exclude_private_notes()
     }
 
-    fn set_directory(&mut self, full_path: PathBuf) -> anyhow::Result<()> {
-        let rel_path = strip_input_dir(&full_path, &self.input_dir)?;
-        if let Some(ext) = full_path.extension() {
-            if ext == ASSETS_DIR_EXT {
-                // TODO: Skip setting this directory path on our FileData. Instead write a list of
-                // file paths directly to a Vec<Path> on FileData files on our FileData struct.
-                // Push them a Vec<PathBuf>
-                self.set_asset_dir(rel_path)?;
-            }
-            return Ok(());
+    /// Copy the asset at `origin`, streaming it out of its archive when its space is a `.zip` file
+    pub(crate) fn copy_asset(&self, origin: &Path, destination: &Path) -> anyhow::Result<()> {
+        let archive = self
+            .archives
+            .iter()
+            .find_map(|a| Some((a, origin.strip_prefix(a.path()).ok()?)));
+        match archive {
+            Some((archive, name)) => archive.copy(name, destination),
+            None => fs::copy(origin, destination)
+                .map(|_| ())
+                .map_err(Into::into),
         }
-        self.directories.insert(rel_path);
-        Ok(())
     }
 
-    fn set_file(&mut self, full_path: PathBuf) -> anyhow::Result<()> {
-        let ext = full_path.extension()
-            .with_context(||
-                format!("Trying to create a FileData entry in HashMap but could not access the file's extension for file = {}", full_path.display())
-            )?;
+    /// exclude_private_notes leaves private notes out of the site and marks drafts
+    ///
+    /// A note is private when
+    ///  * its name (or the name of a directory it is in) contains 🔒
+    ///  * its body contains a #private tag
+    ///  * its frontmatter has `private: true` or `publish: false`
+    ///
+    /// A note is a draft when its name contains 🚧, its body contains a #draft tag or its
+    /// frontmatter has `draft: true`
+    fn exclude_private_notes(&mut self) -> anyhow::Result<()> {
+        let mut private = Vec::new();
+        for (key, file_data) in self.files.iter_mut() {
+            let (frontmatter, body) = Frontmatter::split(&file_data.body).with_context(|| {
+                format!(
+                    "Invalid frontmatter in file = {}",
+                    file_data.path_full.display()
+                )
+            })?;
+            let flag = |name: &str| {
+                frontmatter
+                    .as_ref()
+                    .and_then(|f| f.get_bool(name))
+                    .unwrap_or(false)
+            };
 
-        // We have a file which is non-markdown, meaning it is an asset file. So we do not want to
-        // use it as a key into our HashMap. Instead let set_asset_on_file lookup the corresponding
-        // file in our HashTable to add this file as an associated asset path.
-        if ext != MD_EXT {
-            return self.set_asset_on_file(&full_path, ext);
+            let text = without_code(body);
+            let publish = frontmatter
+                .as_ref()
+                .and_then(|f| f.get_bool("publish"))
+                .unwrap_or(true);
+            let is_private = key.to_string_lossy().contains(UNIC_PRIVATE)
+                || RE_PRIVATE_TAG.is_match(&text)
+                || flag("private")
+                || !publish;
+            if is_private {
+                private.push(key.clone());
+                continue;
+            }
+            file_data.draft = file_data.name.contains(UNIC_DRAFT)
+                || RE_DRAFT_TAG.is_match(&text)
+                || flag("draft");
+        }
+        for key in private {
+            self.files.remove(&key);
+            self.excluded.insert(key);
         }
-
-        let mut file_data = FileData::try_from(full_path.clone())?;
-        // Set path_rel, path_slug
-        file_data.set_paths(&self.input_dir)?;
-        let key = file_data.path_rel.clone();
-        // Insert into HashMap
-        let _ = self.files.insert(key, file_data);
         Ok(())
     }
 
-    fn set_asset_on_file(&mut self, asset_path: &Path, ext: &OsStr) -> anyhow::Result<()> {
-        // No point in adding an ".bin" asset as there will also be a {name}_bin_preview.png in the
-        // same directory
-        if ext == BIN_EXT {
-            return Ok(());
+    /// resolve_slug_collisions finds pages, sections and colocated assets which map to the same
+    /// slug, since write_files would silently overwrite one with the other. Each collision is
+    /// reported and resolved with `Options::on_collision`.
+    ///
+    /// This has to run once process_files has finished since it needs to see every note.
+    ///
+    /// Example:
+    ///     "Aerospace/🚀 Rockets.md" -> "aerospace/rockets.md"
+    ///     "Aerospace/Rockets.md"    -> "aerospace/rockets.md"
+    ///     "Languages/C++/"          -> "languages/c/"
+    ///     "Languages/C/"            -> "languages/c/"
+    pub fn resolve_slug_collisions(&mut self) -> anyhow::Result<Vec<Collision>> {
+        let strategy = self.options.on_collision;
+        let slug_strategy = self.options.slugify_paths;
+        let mut collisions = Vec::new();
+
+        // Every directory which contains a note (a section) and every note (a page), grouped by
+        // their parent directory. A page, a colocated page and a section with the same slug all
+        // end up at the same URL in Zola. The bool is `is_page` so sections sort first and keep
+        // their slug when a page shares their name.
+        let mut children: BTreeMap<PathBuf, BTreeSet<(bool, PathBuf)>> = BTreeMap::new();
+        for (path_rel, file_data) in self.files.iter() {
+            // A section's index note shares its slug with the section
+            let mut is_page = !file_data.section;
+            let mut child = path_rel.as_path();
+            while let Some(parent) = child.parent() {
+                children
+                    .entry(parent.to_path_buf())
+                    .or_default()
+                    .insert((is_page, child.to_path_buf()));
+                is_page = false;
+                child = parent;
+            }
         }
-        // The only {name}_{ext}_preview.png files we want to add to our FileData struct are the ones for
-        // ".bin" files since only the preview can be used in Markdown.
-        // All other {name}_{ext}_preview.png files generated by Craft are useless to us and will
-        // not be copied over to Zola.
-        //
-        // NOTE: The file name includes the extension (e.g. "image.png")
-        let fname = asset_path.file_name().with_context(|| {
-            format!(
-                "Attempted to get an asset file's file_name but failed. File path =  {}",
-                asset_path.display()
-            )
-        })?;
-        let fname = fname.to_str()
-            .with_context(|| {
-                format!(
-                    "Attempted to convert an asset file's OsStr representation to UTF-8 str and failed. File path = {}",
-                    asset_path.display()
+
+        // A parent directory always sorts before its children, so its slug has been resolved by
+        // the time we get to them
+        let mut dir_slugs: HashMap<PathBuf, PathBuf> =
+            HashMap::from([(PathBuf::new(), PathBuf::new())]);
+        let mut page_slugs: HashMap<PathBuf, PathBuf> = HashMap::new();
+        for (parent, entries) in &children {
+            let parent_slug = dir_slugs[parent].clone();
+            let slug_of = |p: &Path| {
+                slugify_component(
+                    &p.file_name().unwrap_or_default().to_string_lossy(),
+                    slug_strategy,
                 )
-            })?;
-        // Don't add files that are previews of non-bin assets (E.g. previews of jpegs or other pngs)
-        let bin_preview = format!("_{BIN_EXT}_preview");
-        if ext == PNG_EXT && !fname.contains(bin_preview.as_str()) {
-            return Ok(());
-        }
+            };
 
-        // Set this asset on our FileData
-        //
-        // NOTE: We are assuming that the FileData for the original markdown file has already been
-        // created at this point based on the walkdir ordering. If there is a error/panic here it
-        // would likely be because there was a file ordering issue.
-        //
-        // NOTE: We are also using the fact that all images/media will live inside a directory
-        // called "{Some Markdown File Name}.assets/{image_asset_path}.{ext}"
-        // Therefore we can get to the file's name using only the image's asset path.
-        let maybe_md_path_rel = {
-            // Example:
-            // Hand Tools Woodworking.assets/image.jpeg
-            // Hand Tools Woodworking.assets
-            let mut asset_dir_path = asset_path.to_path_buf();
-            asset_dir_path.pop();
-            // Remove the 'assets' extension from the directory name to use as a key
-            // Example:
-            // Hand Tools Woodworking.assets
-            // Hand Tools Woodworking
-            asset_dir_path.set_extension("");
-            asset_dir_path = strip_input_dir(&asset_dir_path, &self.input_dir)?;
-            asset_dir_path
-        };
+            let mut taken: HashSet<String> = entries.iter().map(|(_, p)| slug_of(p)).collect();
+            let mut by_slug: BTreeMap<String, Vec<&(bool, PathBuf)>> = BTreeMap::new();
+            for entry in entries {
+                by_slug.entry(slug_of(&entry.1)).or_default().push(entry);
+            }
 
-        let mut file_data = self.files.get_mut(&maybe_md_path_rel);
-        if let Some(ref mut file) = file_data {
-            let file_name_path = {
-                let mut asset_dir_path = asset_path.to_path_buf();
-                asset_dir_path.pop();
-                strip_input_dir(asset_path, &asset_dir_path)?
+            for (slug, group) in by_slug {
+                let mut collision = Collision {
+                    slug: parent_slug.join(&slug),
+                    sources: group.iter().map(|(_, p)| p.clone()).collect(),
+                    renamed: Vec::new(),
+                };
+                for (i, (is_page, path_rel)) in group.into_iter().enumerate() {
+                    let mut new_slug = slug.clone();
+                    if i > 0 {
+                        if let Some(resolved) =
+                            strategy.resolve(&slug, path_rel, |c| taken.contains(c))
+                        {
+                            taken.insert(resolved.clone());
+                            collision.renamed.push(parent_slug.join(&resolved));
+                            new_slug = resolved;
+                        }
+                    }
+                    let slugs = if *is_page {
+                        &mut page_slugs
+                    } else {
+                        &mut dir_slugs
+                    };
+                    slugs.insert(path_rel.clone(), parent_slug.join(new_slug));
+                }
+                if collision.sources.len() > 1 {
+                    collisions.push(collision);
+                }
+            }
+        }
+
+        for (path_rel, file_data) in self.files.iter_mut() {
+            let page_slug = match file_data.section {
+                true => &dir_slugs[path_rel],
+                false => &page_slugs[path_rel],
             };
+            // Colocated notes live in a directory next to their assets (see set_asset_dir)
+            file_data.path_slug = note_path(self.options.backend.as_ref(), page_slug, file_data);
 
-            // Push the asset_path onto the file_data's assets vec, or create it if one does not
-            // exist
-            match &mut file.assets {
-                Some(a) => a.push(file_name_path),
-                None => file.assets = Some(vec![file_name_path]),
+            // Slugified asset names can collide inside the colocated directory as well
+            // Example: "Image 1.jpeg" and "image-1.jpeg"
+            let Some(assets) = &mut file_data.assets else {
+                continue;
             };
+            let page_dir = page_slug.clone();
+            let mut taken: HashSet<PathBuf> = assets.iter().map(|a| a.path_slug.clone()).collect();
+            let mut by_slug: BTreeMap<PathBuf, Vec<&mut Asset>> = BTreeMap::new();
+            for asset in assets.iter_mut() {
+                by_slug
+                    .entry(asset.path_slug.clone())
+                    .or_default()
+                    .push(asset);
+            }
+            for (slug, mut group) in by_slug {
+                if group.len() < 2 {
+                    continue;
+                }
+                group.sort_by(|a, b| a.path_rel.cmp(&b.path_rel));
+                let mut collision = Collision {
+                    slug: page_dir.join(&slug),
+                    sources: group
+                        .iter()
+                        .map(|a| file_data.path_rel.join(&a.path_rel))
+                        .collect(),
+                    renamed: Vec::new(),
+                };
+                let stem = slug
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned();
+                let with_stem = |stem: &str| match slug.extension() {
+                    Some(ext) => {
+                        slug.with_file_name(add_ext(Path::new(stem), &ext.to_string_lossy()))
+                    }
+                    None => slug.with_file_name(stem),
+                };
+                for asset in group.into_iter().skip(1) {
+                    let Some(resolved) =
+                        strategy.resolve(&stem, &asset.path_rel, |c| taken.contains(&with_stem(c)))
+                    else {
+                        continue;
+                    };
+                    let resolved = with_stem(&resolved);
+                    taken.insert(resolved.clone());
+                    collision.renamed.push(page_dir.join(&resolved));
+                    asset.path_slug = resolved;
+                }
+                collisions.push(collision);
+            }
+        }
 
-            return Ok(());
+        for collision in &collisions {
+            let file = collision.sources.first().cloned().unwrap_or_default();
+            let message = format!("slug collision: {collision}");
+            match strategy {
+                CollisionStrategy::Error => {
+                    self.diagnostics.error(Code::SlugCollision, &file, message)
+                }
+                _ => self
+                    .diagnostics
+                    .warning(Code::SlugCollision, &file, message),
+            };
         }
-        println!(
-            "Failed to get a matching file in the HashMap for the asset dir name = {} files = {:#?}",
-            asset_path.display(),
-            self.files
-        );
-        bail!("Bailed at set_asset_on_file");
+        if strategy == CollisionStrategy::Error && !collisions.is_empty() {
+            self.diagnostics.report(self.options.diagnostic_format)?;
+            bail!(
+                "Found {} slug collision(s), rename the notes in Craft or choose another collision strategy",
+                collisions.len()
+            );
+        }
+        Ok(collisions)
     }
 
-    fn set_asset_dir(&mut self, rel_path: PathBuf) -> anyhow::Result<()> {
-        // Remove the 'assets' extension from the directory name to use as a key
-        let mut maybe_file_path = rel_path.clone();
-        maybe_file_path.set_extension("");
-
-        // Find the associated file that matches the name of the asset directory
-        let file = self.files.get_mut(&maybe_file_path);
-        if let Some(file) = file {
-            // If this file has an associated assets directory we will have to co-locate the final
-            // markdown file into the same directory.
-            //
-            // Since the slugified name of the directory and the slugified name of the file should be
-            // identical (given the same rules), we can just add "index" to the end of this slugified
-            // path and it will become the full path including the directory both will live in.
-            //
-            // Example:
-            //  File: "cryptography/aes.md"
-            //  Assets: "cryptography/AES.assets/"
-            //  Final File Path: crypography/aes/index.md
-            //                               ^ will also be the assets dir
-            let file_path_slug = &mut file.path_slug;
-            file_path_slug.set_extension("");
-            file_path_slug.push("index");
-            file_path_slug.set_extension(MD_EXT);
-
-            // Set this directory as the assets_dir on the matching FileData
-            // Example: {INPUT_DIR}/Woodworking/DoveTail Joing.assets/
-            let abs_asset_dir_path = create_input_path(&self.input_dir, &rel_path);
-            file.assets_dir = Some(abs_asset_dir_path);
+    /// apply_state matches every note with what was remembered about it in the previous run and
+    ///  * sets the note's aliases when its URL has changed since
+    ///  * resolves the note's created and updated dates from `Options::date_sources`
+    ///
+    /// The state is updated in place and is expected to be saved once the files are written.
+    ///
+    /// A note is the same note as before when it has the same path in the export, or when it has
+    /// been renamed but its body (the fingerprint) is the same. A rename is only followed when a
+    /// single note has disappeared and a single note has appeared with that body, notes sharing
+    /// their body such as empty notes or copies of a template can not be told apart.
+    ///
+    /// This has to run after resolve_slug_collisions since it needs the final path_slug
+    pub fn apply_state(&mut self, state: &mut State) -> anyhow::Result<()> {
+        let previous = std::mem::take(&mut state.notes);
+        let by_path: HashMap<&Path, &NoteState> =
+            previous.iter().map(|n| (n.path.as_path(), n)).collect();
+        let mut fingerprints: HashMap<PathBuf, String> = HashMap::new();
+        for (key, file_data) in &self.files {
+            let (_, body) = Frontmatter::split(&file_data.body).with_context(|| {
+                format!(
+                    "Invalid frontmatter in file = {}",
+                    file_data.path_full.display()
+                )
+            })?;
+            let fingerprint = state::fingerprint(&RE_FIRST_H1.replace(body, ""));
+            fingerprints.insert(key.clone(), fingerprint);
+        }
+        // Only notes which no longer exist under their old path can have been renamed, into
+        // notes which did not exist before
+        let empty = state::fingerprint("");
+        let mut disappeared: HashMap<&str, Vec<&NoteState>> = HashMap::new();
+        for note in previous
+            .iter()
+            .filter(|n| !self.files.contains_key(&n.path) && n.fingerprint != empty)
+        {
+            disappeared.entry(&note.fingerprint).or_default().push(note);
+        }
+        let mut appeared: HashMap<&str, usize> = HashMap::new();
+        for (key, fingerprint) in &fingerprints {
+            if !by_path.contains_key(key.as_path()) {
+                *appeared.entry(fingerprint).or_default() += 1;
+            }
+        }
+        let renamed_from = |fingerprint: &str| match disappeared.get(fingerprint) {
+            Some(notes) if notes.len() == 1 && appeared.get(fingerprint) == Some(&1) => {
+                Some(notes[0])
+            }
+            _ => None,
+        };
 
-            return Ok(());
+        // Keyed by the full path of the file
+        let mut git_dates: HashMap<PathBuf, Dates> = HashMap::new();
+        if self.options.date_sources.contains(&DateSource::Git) {
+            for space in &self.spaces {
+                let space_dates = dates::git_dates(&space.input_dir, &mut self.diagnostics);
+                git_dates.extend(
+                    space_dates
+                        .into_iter()
+                        .map(|(path, dates)| (space.input_dir.join(path), dates)),
+                );
+            }
         }
-        println!(
-            "Tried to get the matching file for this directory but it doesn't exist. File name = {}, files hashmap = {:?}",
-            maybe_file_path.display(),
-            self.files
-        );
-        bail!("Failed to get the file for this asset directory")
+        let now = dates::now();
+
+        let mut keys: Vec<PathBuf> = self.files.keys().cloned().collect();
+        keys.sort();
+        for key in keys {
+            let url = self.page_url(&self.files[&key]);
+            let file_data = self.files.get_mut(&key).unwrap();
+            // Checked when the fingerprints were taken
+            let (frontmatter, _) = Frontmatter::split(&file_data.body)?;
+            let fingerprint = fingerprints[&key].clone();
+
+            let before = by_path
+                .get(key.as_path())
+                .copied()
+                .or_else(|| renamed_from(&fingerprint));
+            let mut aliases = before.map(|b| b.aliases.clone()).unwrap_or_default();
+            if let Some(before) = before {
+                if before.url != url && !aliases.contains(&before.url) {
+                    aliases.push(before.url.clone());
+                }
+            }
+            // The note may have been renamed back to one of its old names
+            aliases.retain(|alias| *alias != url);
+
+            file_data.aliases = aliases.clone();
+
+            // The ledger remembers when a note was first seen and when its content last changed.
+            // A note seen for the first time takes its dates from the other sources.
+            let ledger = before.map(|b| Dates {
+                created: b.created.clone(),
+                updated: match b.fingerprint == fingerprint {
+                    true => b.updated.clone(),
+                    false => Some(now.clone()),
+                },
+            });
+            let frontmatter = frontmatter.map(|f| Dates {
+                created: f
+                    .get_str(&["created", "date"])
+                    .and_then(|d| dates::parse_date(&d)),
+                updated: f
+                    .get_str(&["updated", "modified"])
+                    .and_then(|d| dates::parse_date(&d)),
+            });
+            let git = git_dates.get(&file_data.path_full).cloned();
+            let fs = Dates {
+                created: Some(file_data.created_at.clone()),
+                updated: Some(file_data.modified_at.clone()),
+            };
+            let Dates { created, updated } =
+                Dates::combine(&self.options.date_sources, |source| match source {
+                    DateSource::Frontmatter => frontmatter.clone(),
+                    DateSource::Ledger => ledger.clone(),
+                    DateSource::Git => git.clone(),
+                    DateSource::Fs => Some(fs.clone()),
+                });
+            file_data.created_at = created.unwrap_or_else(|| now.clone());
+            file_data.modified_at = updated.unwrap_or_else(|| now.clone());
+
+            state.notes.push(NoteState {
+                path: key,
+                fingerprint,
+                url,
+                aliases,
+                created: Some(file_data.created_at.clone()),
+                updated: Some(file_data.modified_at.clone()),
+            });
+        }
+        Ok(())
+    }
+
+    /// The URL the site will publish a note at, see Backend::page_url
+    fn page_url(&self, file_data: &FileData) -> String {
+        // The section directories OUTPUT is in are slugified as well
+        let mount = slugify_path(Path::new(&self.mount), self.options.slugify_paths);
+        self.options.backend.page_url(&mount, file_data)
     }
 
     // Get all of the files as an interator
@@ -532,7 +1461,7 @@ impl CraftDocs {
     //      Replace it with a link that goes nowhere
     //      Example: [Mon, Dec 3](.)
     //
-    //      Find all image links to media inside '.assets' directories
+    //      Find all image and plain links to media inside '.assets' directories
     //      Replace with the file name only
     //      Example:
     //          ![Image.jpeg](Non%20Qualified%20Stock%20Options(NSO).assets/Image.jpeg)
@@ -548,269 +1477,836 @@ impl CraftDocs {
     //
     //
     pub fn format_markdown(&mut self) -> anyhow::Result<()> {
-        let mut files = self.files.clone();
-        for (_path_rel, file_data) in files.iter_mut() {
-            let mut buffer = read_to_string(&file_data.path_full)?;
-
-            // ERROR - Immediately if we find a buffer which contains a markdown link pointing to a
-            // Craft block.
-            // From the web, a link pointing to craftdocs://open?blockID={}&spaceID={} will be
-            // unusable.
-            // Having these links within any document represents an invalid export of the craft
-            // workspace.
-            if let Some(cap) = RE_CRAFTDOCS_LINK.captures(buffer.as_str()) {
-                bail!(
-                    "Invalid document: \n
-                    File = '{}' \n
-                    This document contains a markdown link to an internal or prviate Craft block. \n
-                    The link is in the format of ()[craftdocs://open?blockID={{}}&spaceID={{}}] \n
-                    Link = '{}'",
-                    file_data.path_full.display(),
-                    cap.get(0).unwrap().as_str()
-                );
-            }
+        // Notes are formatted in parallel, each one only reads the others. They are taken in the
+        // order of their keys so the diagnostics and the first error are the same on every run.
+        let mut keys: Vec<PathBuf> = self.files.keys().cloned().collect();
+        keys.sort();
+        let linked = self.assets_linked_from_other_notes();
+        let formatted: Vec<anyhow::Result<Formatted>> = keys
+            .par_iter()
+            .map(|key| self.format_note(&self.files[key], &linked))
+            .collect();
+
+        for (key, formatted) in keys.iter().zip(formatted) {
+            let formatted = formatted?;
+            self.diagnostics.append(formatted.diagnostics);
+            let file_data = self
+                .files
+                .get_mut(key)
+                .expect("formatted notes to be in files");
+            file_data.contents = formatted.contents;
+            file_data.assets = formatted.assets;
+        }
 
-            // Replace the first #H1 Header in the file
-            // This is because Zola will have the file's `title` in the frontmatter we generate
-            // Zola renders the title as an h1 anyway so there is little point in having two titles
-            buffer = RE_FIRST_H1.replace(&buffer, "").into();
-
-            // We are going to format the frontmatter for this markdown file and pre-pend it to the
-            // existing document in place
-            buffer = format!(
-                "---\n\
-                title: \"{}\"\n\
-                date: {}\n\
-                updated: {}\n\
-                weight: {}\n\
-                extra:\n  \
-                note_type: {}\n\
-                ---\n\
-                {}",
-                &file_data.name,
-                &file_data.created_at,
-                file_data.modified_at,
-                file_data.note_type.to_weight(),
-                file_data.note_type.as_emoji(),
-                buffer
+        // ERROR - after every note has been checked, so all broken links are reported at once
+        // instead of one per run. The warnings found while reading the notes are reported here as
+        // well.
+        self.diagnostics.report(self.options.diagnostic_format)?;
+        if self.diagnostics.error_count() > 0 {
+            bail!(
+                "Found {} error(s) while converting the notes",
+                self.diagnostics.error_count()
             );
+        }
+        Ok(())
+    }
 
-            // Find all the [[Wiki Links]] in this buffer and replace them with their
-            // Zola internal link equivalent
-            buffer = self
-                .replace_all(&RE_WIKI_LINK, buffer.as_str(), |caps, m| {
-                    self.replace_wiki_link(caps, m)
-                })
-                .with_context(|| {
-                    format!(
-                        "Got some invalid [[wiki link]] in file = {}",
-                        file_data.path_full.display()
-                    )
-                })?;
-
-            // Find all the date links and [Tues, Jan 4](day://2023.01.04) and replace link portion
-            // with '.'
-            buffer = self
-                .replace_all(&RE_DAY_LINK, buffer.as_str(), |caps, m| {
-                    self.replace_day_link(caps, m)
-                })
-                .with_context(|| {
-                    format!(
-                        "Got some invalid [day://yyyy.mm.dd] in file = {}",
-                        file_data.path_full.display()
-                    )
-                })?;
+    /// Convert the body of a single note into its final contents, see format_markdown
+    fn format_note(
+        &self,
+        file_data: &FileData,
+        linked: &HashSet<(PathBuf, PathBuf)>,
+    ) -> anyhow::Result<Formatted> {
+        let mut diagnostics = Diagnostics::default();
+        // The note's own frontmatter has been applied by apply_state, the frontmatter we
+        // generate below replaces it
+        let (_, body) = Frontmatter::split(&file_data.body)?;
+
+        // Replace the first #H1 Header in the file
+        // This is because Zola will have the file's `title` in the frontmatter we generate
+        // Zola renders the title as an h1 anyway so there is little point in having two titles
+        let mut buffer: String = RE_FIRST_H1.replace(body, "").into();
+
+        // Compare the assets exported with this note against the asset links in its body
+        let assets = self.check_asset_references(file_data, &buffer, linked, &mut diagnostics);
+
+        // ERROR - A markdown link pointing to a Craft block.
+        // From the web, a link pointing to craftdocs://open?blockID={}&spaceID={} will be
+        // unusable.
+        // Having these links within any document represents an invalid export of the craft
+        // workspace.
+        for cap in RE_CRAFTDOCS_LINK.captures_iter(&buffer) {
+            let link = cap.get(0).unwrap();
+            diagnostics
+                .error(
+                    Code::CraftBlockLink,
+                    &file_data.path_full,
+                    format!("the link = {} points to an internal or private Craft block, which can not be opened from the web", link.as_str()),
+                )
+                .span = self.source_span(file_data, &link);
+        }
 
-            // Find all image links to media inside '.assets' directories
-            // Replace with the file name only
-            // Example:
-            //  ![Image.jpeg](Non%20Qualified%20Stock%20Options(NSO).assets/Image.jpeg)
-            //                                      only want this part ----^--------^
-            //  ![Image.jpeg](Image.jpeg)
-            buffer = self
-                .replace_all(&RE_IMG_ASSET_LINK, buffer.as_str(), |cap, m| {
-                    self.replace_img_asset_link(cap, m)
-                })
-                .with_context(|| {
-                    format!(
-                        "Tried to parse an image link but it was invalid in file = {}",
-                        file_data.path_full.display()
-                    )
-                })?;
+        // Rewrite the links and code blocks of the body, see Pipeline
+        buffer = self
+            .options
+            .transforms
+            .run(file_data, buffer, self, &mut diagnostics)?;
+        buffer = self.options.backend.body(buffer);
+
+        // We are going to format the frontmatter for this markdown file and pre-pend it to the
+        // existing document in place
+        let backend = &self.options.backend;
+        let frontmatter = match file_data.section && backend.section_index_name().is_some() {
+            true => backend.section_index(&file_data.name, false),
+            false => backend.frontmatter(file_data),
+        };
+        buffer = frontmatter + &buffer;
 
-            buffer = self.replace_all(&RE_CODE_BLOCK_OTHER, buffer.as_str(), |cap, m| {
-                self.replace_all_code_block_other(cap, m)
-            }).with_context(|| {
-                    format!("Found a code block with syntax 'other' but could not replace it in file = {}", file_data.path_full.display())
-                })?;
+        Ok(Formatted {
+            contents: buffer,
+            assets,
+            diagnostics,
+        })
+    }
 
-            file_data.contents = buffer
+    /// The assets which are linked to from a note they do not belong to, as (key of the note they
+    /// belong to, path inside its '.assets' directory)
+    fn assets_linked_from_other_notes(&self) -> HashSet<(PathBuf, PathBuf)> {
+        let mut linked = HashSet::new();
+        for file_data in self.files.values() {
+            for caps in RE_ASSET_LINK.captures_iter(&file_data.body) {
+                let Ok(decoded) = urlencoding::decode(&caps["file_name"]) else {
+                    continue;
+                };
+                let linked_asset =
+                    self.linked_asset(file_data, &caps["owner"], Path::new(decoded.as_ref()));
+                if let Some((owner, asset)) = linked_asset {
+                    if owner.path_rel != file_data.path_rel {
+                        linked.insert((owner.path_rel.clone(), asset.path_rel.clone()));
+                    }
+                }
+            }
         }
-        self.files = files;
-        Ok(())
+        linked
     }
 
-    // The reference for this replacement routine comes from the Regex documentation.
-    //
-    // When writing a replacement routine where any replacement may fail, you will need to write
-    // your own routine on top of replace_all to handle each Result.
-    //
-    // https://docs.rs/regex/latest/regex/struct.Regex.html#method.replace_all
-    fn replace_all<E>(
-        &self,
-        re: &Regex,
-        haystack: &str,
-        replacement: impl Fn(&Captures, &Match) -> Result<String, E>,
-    ) -> Result<String, E> {
-        let mut new = String::with_capacity(haystack.len());
-        let mut last_match = 0;
-        for caps in re.captures_iter(haystack) {
-            let m = caps.get(0).unwrap();
-            let start_original = m.start();
-            let end_original = m.end();
-            let before = &haystack[last_match..start_original];
-
-            let rep = &replacement(&caps, &m)?;
-
-            new.push_str(before);
-            new.push_str(rep);
-            last_match = end_original;
-        }
-        let after = &haystack[last_match..];
-        new.push_str(after);
-        Ok(new)
-    }
-
-    fn replace_wiki_link(
+    /// Compare the files in a note's '.assets' directory with the asset links in its body
+    ///
+    /// Reports a warning for every asset which is never referenced, these can be left out of the
+    /// site (see `Options::prune_unreferenced_assets`), and an error for every link which points
+    /// to a file that is not in the export. Assets `linked` to from other notes are referenced
+    /// as well, Craft's previews of files it can not show are never reported.
+    ///
+    /// Returns the assets of the note which are copied to the site.
+    fn check_asset_references(
         &self,
-        captures: &Captures,
-        origin_match: &Match,
-    ) -> anyhow::Result<String> {
-        let link_name = captures.name("link_name").context(
-            "Matched on a [[wiki link]] but did not get any value inside the brackets [[ ]]",
-        )?;
-
-        // Does this [[wiki link]] have a Craft Block-ID? (formatted as UUIDv4)
-        // Example: [[Expatriation/Dutch-American Friendship Treaty#^2206D341-3D6E-4F31-B7CF-DD7E3D5D7778]]
-        // Remove it (if no match it returns the original str)
-        let replaced = RE_UUID_V4.replace(link_name.as_str(), "");
-        let mut link_name: &str = replaced.as_ref();
+        file_data: &FileData,
+        buffer: &str,
+        linked: &HashSet<(PathBuf, PathBuf)>,
+        diagnostics: &mut Diagnostics,
+    ) -> Option<Vec<Asset>> {
+        let mut referenced: HashSet<PathBuf> = HashSet::new();
+        for caps in RE_ASSET_LINK.captures_iter(buffer) {
+            // Undecodable links are reported when the link is rewritten
+            let Ok(decoded) = urlencoding::decode(&caps["file_name"]) else {
+                continue;
+            };
+            let path_rel = PathBuf::from(decoded.as_ref());
+            match self.linked_asset(file_data, &caps["owner"], &path_rel) {
+                Some((owner, _)) if owner.path_rel == file_data.path_rel => {
+                    referenced.insert(path_rel);
+                }
+                // Copied next to the note it belongs to
+                Some(_) => {}
+                // The asset of a note which is left out of the site
+                None if self.asset_exists(file_data, &caps["owner"], &path_rel) => {}
+                None => {
+                    let link = caps.get(0).unwrap();
+                    diagnostics
+                        .error(
+                            Code::MissingAsset,
+                            &file_data.path_full,
+                            format!(
+                                "the link = {} points to an asset which is not in the export",
+                                link.as_str()
+                            ),
+                        )
+                        .span = self.source_span(file_data, &link);
+                }
+            }
+        }
 
-        // Does this [[wiki link]] have a header anchor?
-        // Example: [[Weightlifting/Lower Body Exercises/Deadlift Variants#Conventional deadlifts]]
-        //
-        // Split the header anchor out, slugify it, then add it back into the final zola_link
-        // We are left with our file_name which
-        // should match into the Files HashMap
-        let mut header: Option<String> = None;
-        if let Some(h_cap) = RE_HEADER_ANCHOR.captures(link_name) {
-            let m = h_cap.name("header").unwrap();
-            let header_str = format!("#{}", slugify(m.as_str()));
-            header = header_str.into();
-            let m = h_cap.name("link_name").unwrap();
-            link_name = m.as_str();
-        }
-
-        let zola_link = self.make_zola_link(link_name, header).with_context(|| {
+        let mut assets = file_data.assets.clone()?;
+        let is_referenced = |asset: &Asset| {
+            referenced.contains(&asset.path_rel)
+                || linked.contains(&(file_data.path_rel.clone(), asset.path_rel.clone()))
+        };
+        let unreferenced = assets
+            .iter()
+            .filter(|a| !is_referenced(a) && !is_preview(a, &assets));
+        for asset in unreferenced {
+            diagnostics.warning(
+                Code::UnreferencedAsset,
+                &file_data.path_full,
                 format!(
-                    "No such file = {} does not exist in our HashMap.
-                    This is probably because this [[wiki link]] is referencing a block inside Craft.
-                    Because craft will use `^` as a marker for a block link, we cannot use them in Zola",
-                    origin_match.as_str()
-                )
-            })?;
-        Ok(zola_link)
+                    "the asset = {} is never referenced{}",
+                    asset.path_rel.display(),
+                    if self.options.prune_unreferenced_assets {
+                        ", it will not be copied"
+                    } else {
+                        " and can be left out of the site"
+                    }
+                ),
+            );
+        }
+        if self.options.prune_unreferenced_assets {
+            assets.retain(is_referenced);
+        }
+        Some(assets)
     }
 
-    // Names the capture group "desc" for the date string,
-    // "day_url" for everything including day://,
-    // and "date" for the actual yyyy.mm.dd
-    // static ref RE_DAY_LINK: Regex =
-    //     Regex::new(r"\[(?<desc>.*)\]\((?<day_url>day:\/\/(?<date>\d{4}\.\d{2}\.\d{2}))\)").unwrap();
-    fn replace_day_link(
-        &self,
-        captures: &Captures,
-        origin_match: &Match,
-    ) -> anyhow::Result<String> {
-        let date = captures.name("date").context(
-            "Matched on a ()[day://yyyy.mm.dd] link but did not get any value for yyyy.mm.dd",
-        )?;
+    /// The asset a link into an '.assets' directory points to, with the note it belongs to
+    ///
+    /// The linking note's own assets are looked at first whatever the link names the directory,
+    /// since readers other than Craft name it after the note's title. Otherwise `owner` is the
+    /// path of the note the directory belongs to, relative to the linking note.
+    ///
+    /// Example, from "Woodworking/Dovetail Joint":
+    ///     "Dovetail%20Joint", "plan.pdf"  -> "Woodworking/Dovetail Joint", "plan.pdf"
+    ///     "../Tools/Saw", "teeth.png"      -> "Tools/Saw", "teeth.png"
+    pub(crate) fn linked_asset<'a>(
+        &'a self,
+        from: &'a FileData,
+        owner: &str,
+        path_rel: &Path,
+    ) -> Option<(&'a FileData, &'a Asset)> {
+        if let Some(asset) = from.find_asset(path_rel) {
+            return Some((from, asset));
+        }
+        let owner = urlencoding::decode(owner).ok()?;
+        let from_dir = from.path_rel.parent().unwrap_or(Path::new(""));
+        let owner = self.files.get(&normalize(&from_dir.join(owner.as_ref())))?;
+        Some((owner, owner.find_asset(path_rel)?))
+    }
 
-        // Parse the yyyy.mm.dd using the time crate into a Date
-        // Accepted syntax for this macro can be found in the time.rs book
-        // https://time-rs.github.io/book/api/format-description.html
-        let origin_format = format_description!("[year].[month].[day]");
-        // Then reformat that date object into a string to include the year
-        //  "Mon, Jan 3 2023
-        let new_format =
-            format_description!("[weekday repr:short], [month repr:short] [day padding:none] '[year padding:none repr:last_two]");
-        let date_obj = Date::parse(date.as_str(), origin_format)
-            .with_context(
-                || format!("Unable to parse the day:// URL in our link. match = {} url = {} format = [year].[month].[day]",
-                    origin_match.as_str(),
-                    date.as_str())
-            )?;
-        let new_date = date_obj.format(&new_format).with_context(|| {
-            format!(
-                "Unable to format the original date as the new date for match = {} url = {}",
-                origin_match.as_str(),
-                date.as_str()
-            )
-        })?;
+    /// The URL of an asset copied next to the note it belongs to, for links from other notes
+    ///
+    /// Example: "/garden/tools/saw/teeth.png"
+    pub(crate) fn asset_url(&self, owner: &FileData, asset: &Asset) -> String {
+        let mount = slugify_path(Path::new(&self.mount), self.options.slugify_paths);
+        let page_dir = mount.join(owner.path_slug.parent().unwrap_or(Path::new("")));
+        let page_dir: String = page_dir
+            .components()
+            .map(|x| format!("/{}", urlencoding::encode(&x.as_os_str().to_string_lossy())))
+            .collect();
+        format!("{page_dir}/{}", asset.link())
+    }
 
-        // Since date notes are private and are note exported from Craft, remove the URL from the
-        // link
-        //  [Monday, Jan 3 2023](.)
-        let new_date = format!("[{new_date}](javascript:;)");
+    /// The file a link into an '.assets' directory names is in the export, whether or not the
+    /// note it belongs to is converted
+    fn asset_exists(&self, from: &FileData, owner: &str, path_rel: &Path) -> bool {
+        let Ok(owner) = urlencoding::decode(owner) else {
+            return false;
+        };
+        let from_dir = from.path_full.parent().unwrap_or(Path::new(""));
+        let path = normalize(
+            &from_dir
+                .join(add_ext(Path::new(owner.as_ref()), ASSETS_DIR_EXT))
+                .join(path_rel),
+        );
+        let archive = self
+            .archives
+            .iter()
+            .find_map(|a| Some((a, path.strip_prefix(a.path()).ok()?)));
+        match archive {
+            Some((archive, name)) => archive.contains(name),
+            None => path.is_file(),
+        }
+    }
 
-        Ok(new_date)
+    /// Where the text the transforms run on starts in the note's file, past its frontmatter and
+    /// its first H1. None when the reader rewrote the note, see Reader::keeps_source.
+    fn source_offset(&self, file_data: &FileData) -> Option<usize> {
+        if !self.options.reader.keeps_source() {
+            return None;
+        }
+        let (_, body) = Frontmatter::split(&file_data.body).ok()?;
+        let h1 = RE_FIRST_H1.find(body).map_or(0, |m| m.len());
+        Some(file_data.body.len() - body.len() + h1)
     }
 
-    fn replace_img_asset_link(
-        &self,
-        captures: &Captures,
-        origin_match: &Match,
-    ) -> anyhow::Result<String> {
-        let name = captures
-            .name("name")
-            .with_context(|| {
-                format!(
-                    "Failed to get the image link's name from within the brackets [] on text = {}",
-                    origin_match.as_str()
-                )
-            })?
-            .as_str();
-        let file_name = captures
-            .name("file_name")
-            .with_context(|| {
-                format!(
-                    "Failed to get the image link's name from within the brackets [] on text = {}",
-                    origin_match.as_str()
-                )
-            })?
-            .as_str();
+    /// The span in the note's file of `m`, a match in the text the transforms run on
+    ///
+    /// None when the note is not its file (see source_offset) or when an earlier transform
+    /// moved the text so it is not at the same place in the file.
+    pub(crate) fn source_span(&self, file_data: &FileData, m: &regex::Match) -> Option<Span> {
+        let offset = self.source_offset(file_data)?;
+        let range = offset + m.start()..offset + m.end();
+        match file_data.body.get(range.clone()) == Some(m.as_str()) {
+            true => Some(Span::new(&file_data.body, range)),
+            false => None,
+        }
+    }
 
-        let link = format!("![{name}]({file_name})");
-        Ok(link)
+    /// Find the key of the note a [[wiki link]] points to
+    ///
+    /// A link can name the space of the note with a `Space:` prefix. Otherwise the space of the
+    /// note containing the link is searched first, then every other space in order.
+    ///
+    /// Note names may contain a colon too, the prefix is only a space when that space has the
+    /// note. Otherwise the whole link is the note's path.
+    ///
+    /// Example, from a note in the "Engineering" space:
+    ///     [[Cryptography/TLS]]            -> "Engineering/Cryptography/TLS"
+    ///     [[Reading:Books/Cryptonomicon]] -> "Reading/Books/Cryptonomicon"
+    ///     [[Rust: Ownership]]             -> "Engineering/Rust: Ownership"
+    pub(crate) fn resolve_link_key(&self, from: &FileData, link_name: &str) -> PathBuf {
+        if let Some((space_name, note)) = link_name.split_once(':') {
+            let key = self
+                .spaces
+                .iter()
+                .find(|s| s.name == space_name)
+                .map(|space| space.prefix.join(note))
+                .filter(|key| self.files.contains_key(key) || self.excluded.contains(key));
+            if let Some(key) = key {
+                return key;
+            }
+        }
+        let current = self.spaces.iter().filter(|s| s.name == from.space);
+        let others = self.spaces.iter().filter(|s| s.name != from.space);
+        let candidates: Vec<PathBuf> = current
+            .chain(others)
+            .map(|space| space.prefix.join(link_name))
+            .collect();
+        candidates
+            .iter()
+            .find(|key| self.files.contains_key(*key) || self.excluded.contains(*key))
+            .or(candidates.first())
+            .cloned()
+            .unwrap_or_else(|| PathBuf::from(link_name))
     }
 
-    fn replace_all_code_block_other(
+    /// A link from the note `from` to the note with the key `key`, see Backend::link. The text
+    /// of the link is the note's name unless `text` is given.
+    pub(crate) fn make_link(
         &self,
-        _captures: &Captures,
-        _origin_match: &Match,
-    ) -> anyhow::Result<String> {
-        Ok("```".into())
+        from: &FileData,
+        key: &Path,
+        text: Option<&str>,
+        header: Option<String>,
+    ) -> Option<String> {
+        self.files.get(key).map(|to| {
+            let text = text.unwrap_or(&to.name);
+            self.options
+                .backend
+                .link(&self.mount, from, to, text, header.as_deref())
+        })
     }
+}
 
-    fn make_zola_link(&self, key: &str, header: Option<String>) -> Option<String> {
-        self.files.get::<PathBuf>(&key.into()).map(|file_data| {
-            let base_dir_name = slugify(&self.input_dir_name);
-            let header = header.unwrap_or_default();
-            format!(
-                "[{name}](@/{base_dir_name}/{file_path_slug}{header})",
-                name = &file_data.name,
-                file_path_slug = &file_data.path_slug.display(),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{reader::TempDir, zola_files::ZolaFiles, Diagnostic, Severity};
+    use std::fs;
+
+    fn file_data_for(input_dir: &Path, rel: &str) -> FileData {
+        let mut file_data = FileData {
+            path_full: input_dir.join(rel),
+            ..Default::default()
+        };
+        file_data
+            .set_paths(
+                &input_dir.to_path_buf(),
+                Path::new(""),
+                SlugStrategy::default(),
             )
-        })
+            .unwrap();
+        file_data
+    }
+
+    #[test]
+    fn strip_ext_only_removes_the_real_extension() {
+        assert_eq!(
+            strip_ext(Path::new("Releases/Rust 1.70 Release.md"), MD_EXT),
+            PathBuf::from("Releases/Rust 1.70 Release")
+        );
+        assert_eq!(
+            strip_ext(Path::new("Plans/v2.0 Plan.assets"), ASSETS_DIR_EXT),
+            PathBuf::from("Plans/v2.0 Plan")
+        );
+        assert_eq!(
+            strip_ext(Path::new("Plans/v2.0 Plan"), MD_EXT),
+            PathBuf::from("Plans/v2.0 Plan")
+        );
+        assert_eq!(
+            add_ext(Path::new("plans/v2.0-plan"), MD_EXT),
+            PathBuf::from("plans/v2.0-plan.md")
+        );
+    }
+
+    #[test]
+    fn set_paths_keeps_dotted_names() {
+        let file_data = file_data_for(Path::new("/export"), "Releases/Rust 1.70 Release.md");
+        assert_eq!(
+            file_data.path_rel,
+            PathBuf::from("Releases/Rust 1.70 Release")
+        );
+        assert_eq!(
+            file_data.path_slug,
+            PathBuf::from("releases/rust-1-70-release.md")
+        );
+    }
+
+    #[test]
+    fn set_paths_strips_emoji_prefixes() {
+        let file_data = file_data_for(Path::new("/export"), "🚀 Rockets/🌲 Saturn V.md");
+        assert_eq!(file_data.path_rel, PathBuf::from("🚀 Rockets/🌲 Saturn V"));
+        assert_eq!(file_data.path_slug, PathBuf::from("rockets/saturn-v.md"));
+    }
+
+    #[test]
+    fn set_paths_transliterates_unicode() {
+        let file_data = file_data_for(Path::new("/export"), "Café/Straße 2.0.md");
+        assert_eq!(file_data.path_rel, PathBuf::from("Café/Straße 2.0"));
+        assert_eq!(file_data.path_slug, PathBuf::from("cafe/strasse-2-0.md"));
+    }
+
+    #[test]
+    fn asset_names_are_slugified_with_their_extension() {
+        let slug = |p: &str| slugify_asset_path(Path::new(p), SlugStrategy::default());
+        assert_eq!(
+            slug("Drawings/🪚 Image 1.JPEG"),
+            PathBuf::from("drawings/image-1.jpeg")
+        );
+        assert_eq!(slug("Café Menu.pdf"), PathBuf::from("cafe-menu.pdf"));
+
+        // Names without a single letter or digit are told apart by a hash of the original name
+        let saw = slug("🪚.png");
+        assert_eq!(saw, PathBuf::from("asset-cda64cbc.png"));
+        assert_ne!(slug("🔨.png"), saw);
+        assert_ne!(slug("★★★.png"), saw);
+        assert_eq!(slug("Drawings/🪚.png"), Path::new("drawings").join(&saw));
+
+        let asset = Asset::new(PathBuf::from("Drawings/🪚 Image 1.JPEG"), None);
+        assert_eq!(asset.path_slug, asset.path_rel);
+        assert_eq!(asset.link(), "Drawings/%F0%9F%AA%9A%20Image%201.JPEG");
+    }
+
+    #[test]
+    fn assets_dir_keys_match_their_note() {
+        for name in [
+            "Plans/v2.0 Plan",
+            "🚀 Rockets/🌲 Saturn V",
+            "Café/Straße 2.0",
+        ] {
+            let assets_dir = add_ext(Path::new(name), ASSETS_DIR_EXT);
+            assert_eq!(
+                CraftScan::note_key_for_assets_dir(&assets_dir),
+                PathBuf::from(name)
+            );
+        }
+    }
+
+    #[test]
+    fn process_files_joins_assets_to_dotted_notes() {
+        let input_dir = TempDir::new("dotted");
+        input_dir.write("Plans/v2.0 Plan.md", "# v2.0 Plan\n");
+        input_dir.write("Plans/v2.0 Plan.assets/Image.png", "");
+
+        let mut craft = CraftDocs::new(
+            vec![Space::new(input_dir.to_path_buf())],
+            "garden".into(),
+            Options::default(),
+        );
+        craft.process_files().unwrap();
+        let file_data = &craft.files[Path::new("Plans/v2.0 Plan")];
+        assert_eq!(
+            file_data.path_slug,
+            PathBuf::from("plans/v2-0-plan/index.md")
+        );
+        assert_eq!(
+            file_data
+                .find_asset(Path::new("Image.png"))
+                .map(|a| &a.path_slug),
+            Some(&PathBuf::from("Image.png"))
+        );
+    }
+
+    #[test]
+    fn assets_are_joined_to_their_note_in_a_second_pass() {
+        let input_dir = TempDir::new("two-pass");
+        // "Saw.assets" sorts before "Saw.md", its assets are walked before the note
+        input_dir.write("Saw.assets/drawings/teeth.png", "");
+        input_dir.write("Saw.md", "# Saw\n");
+        input_dir.write("Finance/Budget.md", "# Budget\n");
+        input_dir.write("Finance/Orphan.assets/receipt.pdf", "");
+
+        let scan = Craft
+            .read(&Space::new(input_dir.to_path_buf()), &Options::default())
+            .unwrap();
+        let saw = scan
+            .files
+            .iter()
+            .find(|f| f.path_rel == Path::new("Saw"))
+            .unwrap();
+        assert_eq!(saw.assets_dir, Some(input_dir.join("Saw.assets")));
+        assert!(saw.find_asset(Path::new("drawings/teeth.png")).is_some());
+        assert_eq!(saw.path_slug, PathBuf::from("saw/index.md"));
+        assert_eq!(scan.directories, vec![PathBuf::from("Finance")]);
+        assert!(scan
+            .files
+            .iter()
+            .all(|f| f.assets_dir.is_none() || f.name == "Saw"));
+
+        let [orphaned] = &scan.diagnostics.iter().collect::<Vec<_>>()[..] else {
+            panic!("expected a single warning");
+        };
+        assert_eq!(orphaned.code, Code::OrphanedAssets);
+        assert_eq!(orphaned.severity, Severity::Warning);
+        assert_eq!(orphaned.file, input_dir.join("Finance/Orphan.assets"));
+    }
+
+    #[test]
+    fn every_asset_type_is_copied_and_linked() {
+        let input_dir = TempDir::new("asset-types");
+        let output_dir = TempDir::new("asset-types-out");
+        input_dir.write(
+            "Woodworking/Dovetail Joint.md",
+            "# Dovetail Joint\n\n\
+            ![Image 1.jpeg](Dovetail%20Joint.assets/Image%201.jpeg)\n\
+            [plan.pdf](Dovetail%20Joint.assets/plan.pdf)\n\
+            [Chisel.m4a](Dovetail%20Joint.assets/Chisel.m4a)\n\
+            [side.png](Dovetail%20Joint.assets/drawings/side.png)\n",
+        );
+        let assets = [
+            "Chisel.m4a",
+            "Image 1.jpeg",
+            "drawings/side.png",
+            "plan.pdf",
+        ];
+        for asset in assets {
+            input_dir.write(&format!("Woodworking/Dovetail Joint.assets/{asset}"), asset);
+        }
+
+        let conversion = crate::Converter::new(output_dir.to_path_buf())
+            .space(Space::new(input_dir.to_path_buf()))
+            .section("garden")
+            .run()
+            .unwrap();
+        let page_dir = Path::new("woodworking/dovetail-joint");
+        assert_eq!(
+            conversion.written.assets,
+            assets.map(|asset| page_dir.join(asset))
+        );
+        for asset in assets {
+            assert_eq!(
+                fs::read_to_string(output_dir.join(page_dir).join(asset)).unwrap(),
+                asset
+            );
+        }
+        let contents = fs::read_to_string(output_dir.join(page_dir).join("index.md")).unwrap();
+        assert!(contents.ends_with(
+            "\n![Image 1.jpeg](Image%201.jpeg)\n\
+            [plan.pdf](plan.pdf)\n\
+            [Chisel.m4a](Chisel.m4a)\n\
+            [side.png](drawings/side.png)\n"
+        ));
+    }
+
+    #[test]
+    fn asset_references_are_checked_across_notes() {
+        let input_dir = TempDir::new("linked-assets");
+        input_dir.write("Tools/Saw.md", "# Saw\n");
+        for asset in ["teeth.png", "blob.bin", "blob_bin_preview.png"] {
+            input_dir.write(&format!("Tools/Saw.assets/{asset}"), "");
+        }
+        input_dir.write(
+            "Woodworking/Dovetail Joint.md",
+            "# Dovetail Joint\n\n\
+            ![teeth.png](../Tools/Saw.assets/teeth.png)\n\
+            ![gone.png](Dovetail%20Joint.assets/gone.png)\n",
+        );
+
+        let options = Options::default().prune_unreferenced_assets(true);
+        let space = Space::new(input_dir.to_path_buf());
+        let mut craft = CraftDocs::new(vec![space], "garden".into(), options);
+        craft.process_files().unwrap();
+        assert!(craft.format_markdown().is_err());
+
+        let diagnostics: Vec<(Code, &Path, &str)> = craft
+            .diagnostics
+            .iter()
+            .map(|d| (d.code, d.file.as_path(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            [
+                (
+                    Code::UnreferencedAsset,
+                    input_dir.join("Tools/Saw.md").as_path(),
+                    "the asset = blob.bin is never referenced, it will not be copied"
+                ),
+                (
+                    Code::MissingAsset,
+                    input_dir.join("Woodworking/Dovetail Joint.md").as_path(),
+                    "the link = ![gone.png](Dovetail%20Joint.assets/gone.png) points to an asset \
+                    which is not in the export"
+                ),
+            ]
+        );
+
+        // The asset linked to from another note is still copied with the note it belongs to
+        let saw = &craft.files[Path::new("Tools/Saw")];
+        let kept: Vec<&Path> = saw
+            .assets
+            .iter()
+            .flatten()
+            .map(|a| a.path_rel.as_path())
+            .collect();
+        assert_eq!(kept, [Path::new("teeth.png")]);
+        let joint = &craft.files[Path::new("Woodworking/Dovetail Joint")];
+        assert!(joint
+            .contents
+            .contains("![teeth.png](/garden/tools/saw/teeth.png)"));
+    }
+
+    #[test]
+    fn resolve_slug_collisions_suffixes_pages_and_sections() {
+        let input_dir = Path::new("/export");
+        let options = Options {
+            on_collision: CollisionStrategy::Numeric,
+            ..Default::default()
+        };
+        let mut craft = CraftDocs::new(
+            vec![Space::new(input_dir.to_path_buf())],
+            "garden".into(),
+            options,
+        );
+        for rel in [
+            "Aerospace/Rockets.md",
+            "Aerospace/🚀 Rockets.md",
+            "Languages/C/Intro.md",
+            "Languages/C++/Intro.md",
+        ] {
+            let file_data = file_data_for(input_dir, rel);
+            craft.files.insert(file_data.path_rel.clone(), file_data);
+        }
+
+        let collisions = craft.resolve_slug_collisions().unwrap();
+        assert_eq!(collisions.len(), 2);
+        let slug_of = |key: &str| craft.files[Path::new(key)].path_slug.clone();
+        assert_eq!(
+            slug_of("Aerospace/Rockets"),
+            PathBuf::from("aerospace/rockets.md")
+        );
+        assert_eq!(
+            slug_of("Aerospace/🚀 Rockets"),
+            PathBuf::from("aerospace/rockets-2.md")
+        );
+        assert_eq!(
+            slug_of("Languages/C/Intro"),
+            PathBuf::from("languages/c/intro.md")
+        );
+        assert_eq!(
+            slug_of("Languages/C++/Intro"),
+            PathBuf::from("languages/c-2/intro.md")
+        );
+
+        let mut craft = CraftDocs::new(
+            vec![Space::new(input_dir.to_path_buf())],
+            "garden".into(),
+            Options::default(),
+        );
+        let file_data = file_data_for(input_dir, "Rockets.md");
+        craft.files.insert(file_data.path_rel.clone(), file_data);
+        let file_data = file_data_for(input_dir, "🚀 Rockets.md");
+        craft.files.insert(file_data.path_rel.clone(), file_data);
+        assert!(craft.resolve_slug_collisions().is_err());
+    }
+
+    #[test]
+    fn private_notes_are_left_out_with_their_assets() {
+        let input_dir = TempDir::new("private");
+        let output_dir = TempDir::new("private-out");
+        input_dir.write("🔒 Salary.md", "# 🔒 Salary\n");
+        input_dir.write("Taxes.md", "# Taxes\n\nFiled. #private\n");
+        input_dir.write("Taxes.assets/receipt.png", "receipt");
+        input_dir.write("Diary.md", "---\nprivate: true\n---\n# Diary\n");
+        input_dir.write(
+            "Launch.md",
+            "# Launch\n\nOpen to #private-beta users.\n\n\
+            ```sh\necho #private #draft\n```\n\n\
+            Tag notes with `#private` or ``#draft``.\n",
+        );
+        input_dir.write(
+            "Index.md",
+            "# Index\n\nSee [[Taxes]] and [[Launch]].\n\
+            Or [[Taxes|my taxes]] and [[Launch|the launch]].\n",
+        );
+
+        let conversion = crate::Converter::new(output_dir.to_path_buf())
+            .space(Space::new(input_dir.to_path_buf()))
+            .section("garden")
+            .run()
+            .unwrap();
+        assert_eq!(
+            conversion.excluded,
+            ["Diary", "Taxes", "🔒 Salary"].map(PathBuf::from)
+        );
+        assert!(conversion.written.assets.is_empty());
+        assert!(!output_dir.join("taxes").exists());
+
+        let launch = fs::read_to_string(output_dir.join("launch.md")).unwrap();
+        assert!(!launch.contains("draft: true"));
+        let index = fs::read_to_string(output_dir.join("index.md")).unwrap();
+        assert!(index.contains("See Taxes and [Launch](@/garden/launch.md)."));
+        assert!(index.contains("Or my taxes and [the launch](@/garden/launch.md)."));
+    }
+
+    #[test]
+    fn broken_links_point_at_their_place_in_the_file() {
+        let spans = |input_dir: &TempDir, options: Options| {
+            let space = Space::new(input_dir.to_path_buf());
+            let mut craft = CraftDocs::new(vec![space], "garden".into(), options);
+            craft.process_files().unwrap();
+            assert!(craft.format_markdown().is_err());
+            craft
+                .diagnostics
+                .iter()
+                .map(|d| {
+                    assert_eq!(d.code, Code::BrokenLink);
+                    d.span.as_ref().map(|s| (s.line, s.column, s.length))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let craft_dir = TempDir::new("spans-craft");
+        craft_dir.write(
+            "Dovetail Joint.md",
+            "---\nsee_also: \"[[Mortise]]\"\n---\n# Dovetail Joint\n\n\
+            Unlike [[Mortise]], or [[Mortise]].\n",
+        );
+        assert_eq!(
+            spans(&craft_dir, Options::default()),
+            [Some((6, 8, 11)), Some((6, 24, 11))]
+        );
+
+        // The reader adds the title, the body is not the file
+        let bundle_dir = TempDir::new("spans-bundle");
+        bundle_dir.write("Dovetail Joint.textbundle/text.md", "Unlike [[Mortise]].\n");
+        assert_eq!(
+            spans(&bundle_dir, Options::default().reader(crate::TextBundle)),
+            [None]
+        );
+    }
+
+    #[test]
+    fn conversions_are_the_same_on_every_run() {
+        let input_dir = TempDir::new("determinism");
+        for section in ["Woodworking", "Finance", "Reading/Books"] {
+            for i in 0..8 {
+                let note = format!("{section}/Note {i}");
+                input_dir.write(
+                    &format!("{note}.md"),
+                    &format!("# Note {i}\n\n[[{section}/Note {}]]\n", (i + 1) % 8),
+                );
+                input_dir.write(&format!("{note}.assets/unused {i}.png"), "png");
+            }
+        }
+        let convert = |name: &str| {
+            let output_dir = TempDir::new(name);
+            let space = Space::new(input_dir.to_path_buf());
+            let mut craft = CraftDocs::new(vec![space], "garden".into(), Options::default());
+            craft.process_files().unwrap();
+            craft.resolve_slug_collisions().unwrap();
+            craft.apply_state(&mut State::default()).unwrap();
+            craft.format_markdown().unwrap();
+            let written = ZolaFiles::new(output_dir.to_path_buf())
+                .write_files(&craft)
+                .unwrap();
+            let diagnostics: Vec<Diagnostic> = craft.diagnostics.iter().cloned().collect();
+            (written, diagnostics)
+        };
+
+        let (written, diagnostics) = convert("determinism-1");
+        assert_eq!(written.pages.len(), 24);
+        assert_eq!(diagnostics.len(), 24);
+        assert_eq!(convert("determinism-2"), (written, diagnostics));
+    }
+
+    #[test]
+    fn renames_are_only_followed_for_unique_bodies() {
+        let input_dir = TempDir::new("renames");
+        let output_dir = TempDir::new("renames-out");
+        let convert = || {
+            crate::Converter::new(output_dir.to_path_buf())
+                .space(Space::new(input_dir.to_path_buf()))
+                .section("garden")
+                .run()
+                .unwrap()
+        };
+        let body = "Cut the tails first.\n";
+        input_dir.write("Dovetails.md", &format!("# Dovetails\n\n{body}"));
+        input_dir.write("Empty.md", "# Empty\n");
+        input_dir.write("Template 1.md", "# Template 1\n\nTODO\n");
+        input_dir.write("Template 2.md", "# Template 2\n\nTODO\n");
+        convert();
+
+        for old in ["Dovetails", "Empty", "Template 1", "Template 2"] {
+            fs::remove_file(input_dir.join(format!("{old}.md"))).unwrap();
+        }
+        input_dir.write("Dovetail Joint.md", &format!("# Dovetail Joint\n\n{body}"));
+        input_dir.write("Blank.md", "# Blank\n");
+        input_dir.write("Template 3.md", "# Template 3\n\nTODO\n");
+        input_dir.write("Template 4.md", "# Template 4\n\nTODO\n");
+        convert();
+
+        let aliases = |page: &str| {
+            let contents = fs::read_to_string(output_dir.join(page)).unwrap();
+            let (frontmatter, _) = Frontmatter::split(&contents).unwrap();
+            frontmatter.unwrap().get_list("aliases")
+        };
+        assert_eq!(aliases("dovetail-joint.md"), ["/garden/dovetails/"]);
+        for page in ["blank.md", "template-3.md", "template-4.md"] {
+            assert!(aliases(page).is_empty(), "{page}");
+        }
+    }
+
+    #[test]
+    fn wiki_links_resolve_across_spaces() {
+        let work = TempDir::new("space-work");
+        let books = TempDir::new("space-books");
+        work.write("TLS.md", "# TLS\n");
+        work.write("Notes.md", "# Notes\n");
+        books.write("Cryptonomicon.md", "# Cryptonomicon\n");
+        books.write("Notes.md", "# Notes\n");
+        work.write("Rust: Ownership.md", "# Rust: Ownership\n");
+        work.write("Books: A Reading List.md", "# Books: A Reading List\n");
+
+        let spaces = vec![
+            Space::named("Work".into(), work.to_path_buf()),
+            Space::named("Books".into(), books.to_path_buf()),
+        ];
+        let mut craft = CraftDocs::new(spaces, "garden".into(), Options::default());
+        craft.process_files().unwrap();
+        let from = &craft.files[Path::new("Work/TLS")];
+        assert_eq!(from.path_slug, PathBuf::from("work/tls.md"));
+
+        let key_of = |link: &str| craft.resolve_link_key(from, link);
+        assert_eq!(key_of("Notes"), PathBuf::from("Work/Notes"));
+        assert_eq!(key_of("Books:Notes"), PathBuf::from("Books/Notes"));
+        assert_eq!(
+            key_of("Rust: Ownership"),
+            PathBuf::from("Work/Rust: Ownership")
+        );
+        assert_eq!(
+            key_of("Books: A Reading List"),
+            PathBuf::from("Work/Books: A Reading List")
+        );
+        assert_eq!(
+            key_of("Cryptonomicon"),
+            PathBuf::from("Books/Cryptonomicon")
+        );
     }
 }
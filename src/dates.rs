@@ -0,0 +1,187 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use time::{
+    format_description::well_known::Rfc3339, macros::format_description, Date, OffsetDateTime,
+};
+
+use crate::transforms::{Code, Diagnostics};
+
+/// Where a note's `date` (created) and `updated` come from
+///
+/// Sources are combined in the order they are given, the first source which knows a date wins.
+/// `created` and `updated` are resolved independently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[non_exhaustive]
+pub enum DateSource {
+    /// `created`/`date` and `updated`/`modified` in the note's YAML frontmatter
+    Frontmatter,
+    /// When the note was first seen and when its content last changed, remembered in the state
+    /// file between runs
+    Ledger,
+    /// The first and the last commit touching the note, when the export directory is part of a
+    /// git repository
+    Git,
+    /// The file's creation and modification time. Falls back to the modification time on file
+    /// systems which do not record a creation time.
+    Fs,
+}
+
+impl DateSource {
+    pub const DEFAULT_ORDER: [DateSource; 4] =
+        [Self::Frontmatter, Self::Ledger, Self::Git, Self::Fs];
+}
+
+/// The created and updated dates one source knows about a note
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Dates {
+    pub created: Option<String>,
+    pub updated: Option<String>,
+}
+
+impl Dates {
+    /// Combine what the sources know about a note, asking them in `order`. The first source
+    /// which knows a date wins, `created` and `updated` are resolved independently.
+    pub fn combine(order: &[DateSource], dates_from: impl Fn(DateSource) -> Option<Dates>) -> Self {
+        let sources: Vec<Dates> = order.iter().filter_map(|s| dates_from(*s)).collect();
+        Self {
+            created: sources.iter().find_map(|d| d.created.clone()),
+            updated: sources.iter().find_map(|d| d.updated.clone()),
+        }
+    }
+}
+
+pub fn now() -> String {
+    OffsetDateTime::now_utc()
+        .format(&Rfc3339)
+        .expect("The current time to be formattable as RFC 3339")
+}
+
+/// Validate a date from the frontmatter, Zola accepts both RFC 3339 and a plain date
+///
+/// Example: "2023-01-04T10:00:00+01:00" or "2023-01-04"
+pub fn parse_date(s: &str) -> Option<String> {
+    let s = s.trim();
+    if OffsetDateTime::parse(s, &Rfc3339).is_ok()
+        || Date::parse(s, format_description!("[year]-[month]-[day]")).is_ok()
+    {
+        return Some(s.into());
+    }
+    None
+}
+
+/// The dates of every file in `input_dir` according to git, keyed by the path relative to
+/// `input_dir` (including the `.md` extension)
+///
+/// Returns an empty map when the directory is not part of a git repository, or when git can not
+/// be run which is reported to `diagnostics`
+pub fn git_dates(input_dir: &Path, diagnostics: &mut Diagnostics) -> HashMap<PathBuf, Dates> {
+    // Every commit is printed as "\0{author date}" followed by the files it touched
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(input_dir)
+        .args([
+            "-c",
+            "core.quotePath=false",
+            "log",
+            "--format=%x00%aI",
+            "--name-only",
+            "--relative",
+            "--",
+            ".",
+        ])
+        .output();
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        Ok(_) => return HashMap::new(),
+        Err(e) => {
+            diagnostics.warning(
+                Code::GitUnavailable,
+                input_dir,
+                format!("unable to run git to look up the dates of the notes, error = {e}"),
+            );
+            return HashMap::new();
+        }
+    };
+
+    parse_git_log(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Read the dates of every file out of the output of the git log in git_dates
+fn parse_git_log(log: &str) -> HashMap<PathBuf, Dates> {
+    // git log lists the newest commit first
+    let mut dates: HashMap<PathBuf, Dates> = HashMap::new();
+    for commit in log.split('\0') {
+        let mut lines = commit.lines().filter(|l| !l.trim().is_empty());
+        let Some(date) = lines.next() else {
+            continue;
+        };
+        for file in lines {
+            let entry = dates.entry(PathBuf::from(file)).or_default();
+            entry.updated.get_or_insert_with(|| date.into());
+            entry.created = Some(date.into());
+        }
+    }
+    dates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_first_source_which_knows_a_date_wins() {
+        let dates = |created: Option<&str>, updated: Option<&str>| Dates {
+            created: created.map(String::from),
+            updated: updated.map(String::from),
+        };
+        let dates_from = |source| match source {
+            DateSource::Frontmatter => Some(dates(Some("2020-01-01"), None)),
+            DateSource::Ledger => Some(dates(Some("2021-01-01"), Some("2021-06-01"))),
+            DateSource::Git => None,
+            DateSource::Fs => Some(dates(Some("2023-01-01"), Some("2023-06-01"))),
+        };
+        assert_eq!(
+            Dates::combine(&DateSource::DEFAULT_ORDER, dates_from),
+            dates(Some("2020-01-01"), Some("2021-06-01"))
+        );
+        assert_eq!(
+            Dates::combine(&[DateSource::Git, DateSource::Fs], dates_from),
+            dates(Some("2023-01-01"), Some("2023-06-01"))
+        );
+        assert_eq!(
+            Dates::combine(&[DateSource::Frontmatter], dates_from),
+            dates(Some("2020-01-01"), None)
+        );
+    }
+
+    #[test]
+    fn git_log_gives_the_first_and_last_commit_of_every_file() {
+        let log = "\x002023-03-01T10:00:00+01:00\n\n\
+            Woodworking/Dovetail Joint.md\n\
+            \x002023-02-01T10:00:00+01:00\n\n\
+            Woodworking/Dovetail Joint.md\n\
+            Café.md\n\
+            \x002023-01-01T10:00:00+01:00\n\n\
+            Woodworking/Dovetail Joint.md\n";
+        let dates = parse_git_log(log);
+        assert_eq!(dates.len(), 2);
+        assert_eq!(
+            dates[Path::new("Woodworking/Dovetail Joint.md")],
+            Dates {
+                created: Some("2023-01-01T10:00:00+01:00".into()),
+                updated: Some("2023-03-01T10:00:00+01:00".into()),
+            }
+        );
+        assert_eq!(
+            dates[Path::new("Café.md")],
+            Dates {
+                created: Some("2023-02-01T10:00:00+01:00".into()),
+                updated: Some("2023-02-01T10:00:00+01:00".into()),
+            }
+        );
+    }
+}
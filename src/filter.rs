@@ -0,0 +1,93 @@
+use std::path::Path;
+
+use anyhow::Context;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// NoteFilter decides which notes of the export make it into the site using gitignore-style
+/// patterns. Patterns are matched against a note's path relative to the export's root, including
+/// the `.md` extension, and against every directory the note is in.
+///
+/// Example:
+/// ```text
+/// Inbox/
+/// Templates/
+/// Daily Notes/
+/// *WIP*.md
+/// !Inbox/Reading List.md
+/// ```
+pub struct NoteFilter {
+    /// `--exclude` patterns and the patterns of the ignore file in the export's root
+    exclude: Gitignore,
+    /// `--include` patterns, when there are any a note has to match one of them
+    include: Option<Gitignore>,
+}
+
+impl NoteFilter {
+    /// The name of the ignore file in the root of the export
+    pub const IGNORE_FILE: &'static str = ".craftzolaignore";
+
+    pub fn new(input_dir: &Path, include: &[String], exclude: &[String]) -> anyhow::Result<Self> {
+        let mut builder = GitignoreBuilder::new(input_dir);
+        let ignore_file = input_dir.join(Self::IGNORE_FILE);
+        if ignore_file.is_file() {
+            if let Some(e) = builder.add(&ignore_file) {
+                return Err(e).with_context(|| {
+                    format!(
+                        "Invalid pattern in the ignore file = {}",
+                        ignore_file.display()
+                    )
+                });
+            }
+        }
+        for pattern in exclude {
+            builder
+                .add_line(None, pattern)
+                .with_context(|| format!("Invalid --exclude pattern = {pattern}"))?;
+        }
+        let exclude = builder.build()?;
+
+        let include = match include.is_empty() {
+            true => None,
+            false => {
+                let mut builder = GitignoreBuilder::new(input_dir);
+                for pattern in include {
+                    builder
+                        .add_line(None, pattern)
+                        .with_context(|| format!("Invalid --include pattern = {pattern}"))?;
+                }
+                Some(builder.build()?)
+            }
+        };
+        Ok(Self { exclude, include })
+    }
+
+    /// Is the note at `path` (relative to the export's root) left out of the site
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        if let Some(include) = &self.include {
+            if !include.matched_path_or_any_parents(path, false).is_ignore() {
+                return true;
+            }
+        }
+        self.exclude
+            .matched_path_or_any_parents(path, false)
+            .is_ignore()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn include_and_exclude_patterns() {
+        let include = vec!["Engineering/".to_string(), "Reading/*.md".to_string()];
+        let exclude = vec!["Engineering/Inbox/".to_string(), "*WIP*.md".to_string()];
+        let filter = NoteFilter::new(Path::new("/export"), &include, &exclude).unwrap();
+
+        assert!(!filter.is_excluded(Path::new("Engineering/Rust/Lifetimes.md")));
+        assert!(!filter.is_excluded(Path::new("Reading/Dune.md")));
+        assert!(filter.is_excluded(Path::new("Personal/Taxes.md")));
+        assert!(filter.is_excluded(Path::new("Engineering/Inbox/Links.md")));
+        assert!(filter.is_excluded(Path::new("Engineering/WIP Design.md")));
+    }
+}
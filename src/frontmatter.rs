@@ -0,0 +1,104 @@
+use anyhow::Context;
+use regex::Regex;
+use serde_yaml::{Mapping, Value};
+
+lazy_static! {
+    // A YAML frontmatter block at the very top of a note
+    // Names the capture group "yaml" for everything between the two `---` lines
+    static ref RE_FRONTMATTER: Regex =
+        Regex::new(r"\A---[ \t]*\r?\n(?<yaml>(?s:.*?))\r?\n?---[ \t]*(\r?\n|\z)").unwrap();
+}
+
+/// Frontmatter is the YAML block a note may start with
+///
+/// Craft does not write frontmatter itself, but it lets us override what we would otherwise
+/// derive from the export.
+///
+/// Example:
+/// ```markdown
+/// ---
+/// created: 2021-03-14
+/// private: true
+/// ---
+/// # Dovetail Joint
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Frontmatter(Mapping);
+
+impl Frontmatter {
+    /// Split the frontmatter off the top of a note's body
+    ///
+    /// Returns the frontmatter (if there is any) and the rest of the body
+    pub fn split(buffer: &str) -> anyhow::Result<(Option<Self>, &str)> {
+        let Some(caps) = RE_FRONTMATTER.captures(buffer) else {
+            return Ok((None, buffer));
+        };
+        let yaml = caps.name("yaml").map_or("", |m| m.as_str());
+        let body = &buffer[caps.get(0).unwrap().end()..];
+        let mapping = match serde_yaml::from_str::<Value>(yaml)
+            .context("Failed to parse the note's YAML frontmatter")?
+        {
+            Value::Mapping(mapping) => mapping,
+            Value::Null => Mapping::new(),
+            other => anyhow::bail!(
+                "Expected the note's frontmatter to be a YAML mapping but got = {other:?}"
+            ),
+        };
+        Ok((Some(Self(mapping)), body))
+    }
+
+    /// The first of `keys` which has a scalar value, formatted as a string
+    pub fn get_str(&self, keys: &[&str]) -> Option<String> {
+        keys.iter().find_map(|key| match self.0.get(*key)? {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        })
+    }
+
+    /// The strings of the list at `key`, a single string is a list of one
+    ///
+    /// Example: `aliases: [Dovetails, Dovetail Joints]`
+    pub fn get_list(&self, key: &str) -> Vec<String> {
+        match self.0.get(key) {
+            Some(Value::Sequence(values)) => values
+                .iter()
+                .filter_map(|v| v.as_str())
+                .map(String::from)
+                .collect(),
+            Some(Value::String(s)) => vec![s.clone()],
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.0.get(key)? {
+            Value::Bool(b) => Some(*b),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_frontmatter_from_body() {
+        let (frontmatter, body) =
+            Frontmatter::split("---\ncreated: 2021-03-14\nweight: 2\n---\n# Title\n").unwrap();
+        let frontmatter = frontmatter.unwrap();
+        assert_eq!(
+            frontmatter.get_str(&["date", "created"]),
+            Some("2021-03-14".into())
+        );
+        assert_eq!(frontmatter.get_str(&["weight"]), Some("2".into()));
+        assert_eq!(body, "# Title\n");
+
+        let (frontmatter, body) = Frontmatter::split("# Title\n---\n").unwrap();
+        assert_eq!(frontmatter, None);
+        assert_eq!(body, "# Title\n---\n");
+    }
+}
@@ -0,0 +1,92 @@
+use std::path::Path;
+
+use crate::{
+    backend::{yaml_aliases, Backend},
+    craft_files::FileData,
+};
+
+/// Hugo, chosen with `--target hugo`
+///
+/// A note with assets becomes a leaf bundle (`index.md` next to its assets) and every directory a
+/// branch bundle (`_index.md`). Links use the `ref` shortcode so Hugo checks them when building.
+///
+/// Example link: [Dovetail Joint]({{< ref "/garden/woodworking/dovetail-joint/index.md#marking-out" >}})
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Hugo;
+
+impl Backend for Hugo {
+    fn name(&self) -> &str {
+        "hugo"
+    }
+
+    fn link(
+        &self,
+        mount: &str,
+        _from: &FileData,
+        to: &FileData,
+        text: &str,
+        header: Option<&str>,
+    ) -> String {
+        // The path is quoted in the shortcode so it may contain spaces
+        let path = Path::new("/").join(mount).join(&to.path_slug);
+        format!(
+            "[{text}]({{{{< ref \"{path}{header}\" >}}}})",
+            path = path.display(),
+            header = header.unwrap_or_default(),
+        )
+    }
+
+    fn frontmatter(&self, note: &FileData) -> String {
+        // Hugo has no `extra` table, custom values live in `params`
+        format!(
+            "---\n\
+            title: \"{}\"\n\
+            date: {}\n\
+            lastmod: {}\n\
+            weight: {}\n\
+            {}\
+            {}\
+            params:\n  \
+            note_type: {}\n\
+            ---\n",
+            &note.name,
+            &note.created_at,
+            note.modified_at,
+            note.note_type.to_weight(),
+            if note.draft { "draft: true\n" } else { "" },
+            yaml_aliases(note),
+            note.note_type.as_emoji(),
+        )
+    }
+
+    fn section_index(&self, title: &str, root: bool) -> String {
+        // Hugo sorts the pages of a section by weight by default
+        format!(
+            "---\n\
+            title: \"{title}\"\n\
+            {}\
+            ---\n",
+            if root { "layout: \"garden\"\n" } else { "" }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn links_use_the_ref_shortcode() {
+        let from = FileData::default();
+        let to = FileData {
+            name: "Dovetail Joint".into(),
+            path_slug: PathBuf::from("woodworking/dovetail-joint/index.md"),
+            ..Default::default()
+        };
+        assert_eq!(
+            Hugo.link("garden", &from, &to, "Dovetails", Some("#marking-out")),
+            "[Dovetails]({{< ref \"/garden/woodworking/dovetail-joint/index.md#marking-out\" >}})"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Convert Craft exported Markdown into a Zola content section
+//!
+//! Craft exports a space as a directory of Markdown files with `[[wiki links]]`, `day://` links
+//! and a `.assets` directory next to every note with attachments. This crate turns such an export
+//! into Zola pages and sections with internal `@/` links, frontmatter and colocated assets.
+//!
+//! Other note apps are read into the same model with a [`Reader`], see [`InputFormat`].
+//!
+//! The `craft-zola` binary is a thin CLI on top of [`Converter`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use craft_zola::{CollisionStrategy, Converter, Options, Space};
+//!
+//! let options = Options::default()
+//!     .slugify_assets(true)
+//!     .on_collision(CollisionStrategy::Numeric)
+//!     .exclude("Inbox/");
+//! let conversion = Converter::new("site/content/garden")
+//!     .space(Space::new("Exports/Garden".into()))
+//!     .options(options)
+//!     .run()?;
+//! println!("Wrote {} pages", conversion.written.pages.len());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! The steps [`Converter::run`] goes through are public as well, for callers who want to look at
+//! or change the notes in between:
+//!
+//! ```no_run
+//! use craft_zola::{CraftDocs, Options, Space, State, ZolaFiles};
+//! use std::path::Path;
+//!
+//! let output_dir = Path::new("site/content/garden");
+//! let mount = ZolaFiles::mount(output_dir, None)?;
+//! let mut craft = CraftDocs::new(vec![Space::new("Exports/Garden".into())], mount, Options::default());
+//! craft.process_files()?;
+//! craft.resolve_slug_collisions()?;
+//! let mut state = State::load(output_dir)?;
+//! craft.apply_state(&mut state)?;
+//! craft.format_markdown()?;
+//! for file_data in craft.files.values() {
+//!     println!("{} -> {}", file_data.path_rel.display(), file_data.path_slug.display());
+//! }
+//! ZolaFiles::new(output_dir.to_path_buf()).write_files(&craft)?;
+//! state.save(output_dir)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! # Stability
+//!
+//! Everything exported from the crate root follows semantic versioning. The modules behind it are
+//! private, so their layout can change in any release.
+//!
+//! Structs which are expected to grow are `#[non_exhaustive]`: their fields can be read, but they
+//! are built with their constructors, `Default` and the builder methods. Adding a field, a builder
+//! method or a variant to a `#[non_exhaustive]` enum is not a breaking change. Errors are
+//! `anyhow::Error`s, their messages are meant for people and are not part of the API.
+
+#[macro_use]
+extern crate lazy_static;
+
+use std::path::PathBuf;
+
+mod archive;
+mod astro_files;
+mod backend;
+mod craft_files;
+mod dates;
+mod filter;
+mod frontmatter;
+mod hugo_files;
+mod logseq_files;
+mod mdbook_files;
+mod notion_files;
+mod obsidian_files;
+mod reader;
+mod slugs;
+mod state;
+mod textbundle_files;
+mod transforms;
+mod zola_files;
+
+pub use astro_files::Astro;
+pub use backend::{write_files, Backend, Target, Written};
+pub use craft_files::{Asset, Craft, CraftDocs, FileData, NoteType, Options, Space};
+pub use dates::DateSource;
+pub use hugo_files::Hugo;
+pub use logseq_files::Logseq;
+pub use mdbook_files::MdBook;
+pub use notion_files::Notion;
+pub use obsidian_files::Obsidian;
+pub use reader::{InputFormat, Reader, Scan};
+pub use slugs::{Collision, CollisionStrategy, SlugStrategy};
+pub use state::{NoteState, State};
+pub use textbundle_files::TextBundle;
+pub use transforms::{
+    AssetLinks, Code, CodeFences, DayLinks, Diagnostic, DiagnosticFormat, Diagnostics, Pipeline,
+    Severity, Span, Transform, WikiLinks,
+};
+pub use zola_files::{CraftZolaConfig, ExtraConfig, SlugifyConfig, Zola, ZolaConfig, ZolaFiles};
+
+/// Converter runs every step of a conversion, from reading the export to writing the Zola files
+/// and the state file
+#[derive(Debug, Clone)]
+pub struct Converter {
+    spaces: Vec<Space>,
+    output_dir: PathBuf,
+    section: Option<String>,
+    options: Options,
+}
+
+impl Converter {
+    /// A conversion into OUTPUT_DIR, add at least one space to convert
+    pub fn new(output_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            spaces: Vec::new(),
+            output_dir: output_dir.into(),
+            section: None,
+            options: Options::default(),
+        }
+    }
+
+    /// Add an exported Craft space, see [`Space`]
+    pub fn space(mut self, space: Space) -> Self {
+        self.spaces.push(space);
+        self
+    }
+
+    /// The path of OUTPUT_DIR inside Zola's `content/` directory, see [`ZolaFiles::mount`]
+    pub fn section(mut self, section: impl Into<String>) -> Self {
+        self.section = Some(section.into());
+        self
+    }
+
+    pub fn options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn run(self) -> anyhow::Result<Conversion> {
+        if self.spaces.is_empty() {
+            anyhow::bail!("Nothing to convert, add at least one space");
+        }
+        let mount = ZolaFiles::mount_in(
+            &self.output_dir,
+            self.options.backend.content_dir(),
+            self.section.as_deref(),
+        )?;
+        let content_dir = self.options.backend.content_dir();
+        let unmounted = self.section.is_none()
+            && zola_files::content_path(&self.output_dir, content_dir).is_none();
+        let unmounted = unmounted.then(|| {
+            format!(
+                "OUTPUT is not inside a {} directory, internal links assume it is mounted at '{mount}', pass --section to change it",
+                content_dir.display(),
+            )
+        });
+        let mut state = State::load(&self.output_dir)?;
+        let zola = ZolaFiles::new(self.output_dir.clone());
+        let mut craft = CraftDocs::new(self.spaces, mount, self.options);
+        if let Some(message) = unmounted {
+            craft
+                .diagnostics
+                .warning(Code::UnmountedOutput, &self.output_dir, message);
+        }
+        craft.process_files()?;
+        let collisions = craft.resolve_slug_collisions()?;
+        craft.apply_state(&mut state)?;
+        craft.format_markdown()?;
+        let written = zola.write_files(&craft)?;
+        state.save(&self.output_dir)?;
+
+        let mut excluded: Vec<PathBuf> = craft.excluded.into_iter().collect();
+        excluded.sort();
+        Ok(Conversion {
+            written,
+            collisions,
+            excluded,
+        })
+    }
+}
+
+/// What a [`Converter`] did
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct Conversion {
+    /// The files written to OUTPUT_DIR
+    pub written: Written,
+    /// The slug collisions which were resolved, empty with [`CollisionStrategy::Error`] as the
+    /// conversion stops on the first one
+    pub collisions: Vec<Collision>,
+    /// The keys of the notes left out of the site because they are private or filtered out
+    ///
+    /// Example: "Journal/🔒 Salary Negotiation"
+    pub excluded: Vec<PathBuf>,
+}
@@ -0,0 +1,613 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use regex::{Captures, Regex};
+use serde_yaml::{Mapping, Value};
+use time::{macros::format_description, Date};
+use walkdir::WalkDir;
+
+use crate::{
+    craft_files::{strip_ext, Asset, FileData, Options, Space, MD_EXT},
+    reader::{asset_link, is_visible, with_title, Reader, Scan},
+    transforms::{Code, Diagnostics},
+};
+
+lazy_static! {
+    // A `key:: value` property of a page or a block
+    static ref RE_PROPERTY: Regex =
+        Regex::new(r"^(?<key>[A-Za-z0-9_\-]+)::[ \t]*(?<value>.*)$").unwrap();
+    // The first line of a block, names the capture group "indent" for the whitespace in front of
+    // the bullet and "content" for the rest of the line
+    static ref RE_BULLET: Regex = Regex::new(r"^(?<indent>\s*)-(?:[ \t](?<content>.*))?$").unwrap();
+    // A ((block reference)) or an {{embed ((block reference))}}
+    static ref RE_BLOCK_REF: Regex = Regex::new(
+        r"(?:\{\{embed\s+)?\(\((?<uuid>[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12})\)\)(?:\s*\}\})?"
+    )
+    .unwrap();
+    // A [label]([[page]]) link or a [[page]] / #[[page]] link
+    static ref RE_PAGE_LINK: Regex = Regex::new(
+        r"\[(?<label>[^\[\]]*)\]\(\[\[(?<labelled>[^\[\]]+)\]\]\)|#?\[\[(?<name>[^\[\]]+)\]\]"
+    )
+    .unwrap();
+    // A link into the graph's `assets/` folder, Logseq writes them relative to the page and may
+    // add the size of an image after it
+    // Example: ![Image 1.png](../assets/image_1680000000000_0.png){:height 100, :width 200}
+    static ref RE_ASSET: Regex = Regex::new(
+        r"(?<bang>!?)\[(?<text>[^\[\]]*)\]\((?:\.\./)*assets/(?<path>[^()\s]+)\)(?:\{[^{}]*\})?"
+    )
+    .unwrap();
+}
+
+const PAGES_DIR: &str = "pages";
+const JOURNALS_DIR: &str = "journals";
+const ASSETS_DIR: &str = "assets";
+/// Logseq stores the page "Woodworking/Dovetail Joint" as "Woodworking___Dovetail Joint.md"
+const NAMESPACE_SEPARATOR: &str = "___";
+
+/// Logseq, chosen with `--from logseq` or `--from logseq-outline`
+///
+/// Reads the `pages/` and `journals/` of a graph. A namespaced page becomes a note inside a
+/// section per namespace, journals are notes of the `journals` section titled like Logseq does by
+/// default.
+///
+/// The outline of a page is either flattened, every block becomes a paragraph of its own, or kept
+/// as nested lists. Page properties (`key:: value` in front of the first block) become the
+/// note's frontmatter, `title::` replaces the note's name, `alias::` names the page for links and
+/// `public:: false` leaves it out. Block properties are dropped. A `((block reference))` is
+/// replaced by the block's text and a link to a page which was never written becomes plain text,
+/// as Logseq creates pages on the fly.
+///
+/// Example:
+///     "- See [[Woodworking/Dovetail Joint]]" -> "See [[Woodworking/Dovetail Joint]]"
+///     "- ![plan.png](../assets/plan_16800.png)" -> "![plan.png](Tools.assets/assets/plan_16800.png)"
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Logseq {
+    /// Keep the outline as nested lists instead of flattening it into paragraphs
+    pub outline: bool,
+}
+
+impl Reader for Logseq {
+    fn name(&self) -> &str {
+        "logseq"
+    }
+
+    fn read(&self, space: &Space, options: &Options) -> anyhow::Result<Scan> {
+        let graph = Graph::load(&space.input_dir, self.outline)?;
+        let slugify_name = options.slugify_assets.then_some(options.slugify_paths);
+        let mut files = Vec::new();
+        let mut directories = BTreeSet::new();
+        let mut diagnostics = Diagnostics::default();
+        for (key, page) in &graph.pages {
+            let mut file_data = FileData::try_from(page.full_path.clone())?;
+            file_data.set_key(key, &space.prefix, options.slugify_paths);
+            file_data.name = page.title.rsplit('/').next().unwrap_or_default().into();
+            directories.extend(key.ancestors().skip(1).map(|dir| space.prefix.join(dir)));
+
+            let mut assets = BTreeSet::new();
+            let body = graph.rewrite(page, &file_data.name, &mut assets, &mut diagnostics);
+            let body = match page.properties.is_empty() {
+                true => body,
+                false => format!(
+                    "---\n{}---\n{body}",
+                    serde_yaml::to_string(&page.properties)?
+                ),
+            };
+            file_data.body = with_title(&body, &file_data.name);
+            if !assets.is_empty() {
+                file_data.assets = Some(
+                    assets
+                        .into_iter()
+                        .map(|path_rel| Asset::new(path_rel, slugify_name))
+                        .collect(),
+                );
+                file_data.assets_dir = Some(space.input_dir.clone());
+                let page_slug = strip_ext(&file_data.path_slug, MD_EXT);
+                file_data.path_slug = options.backend.page_path(&page_slug, true);
+            }
+            files.push(file_data);
+        }
+        directories.remove(&space.prefix);
+        Ok(Scan {
+            files,
+            directories: directories.into_iter().collect(),
+            diagnostics,
+            ..Default::default()
+        })
+    }
+}
+
+/// A page of the graph
+#[derive(Debug)]
+struct Page {
+    full_path: PathBuf,
+    /// Example: "Woodworking/Dovetail Joint", "Jan 4th, 2023"
+    title: String,
+    /// The page properties as the note's frontmatter
+    properties: Mapping,
+    /// The page's body with its outline flattened or normalised
+    body: String,
+}
+
+/// Every page of a graph
+#[derive(Debug, Default)]
+struct Graph {
+    /// The pages by their key, the path of the note inside the space without `.md`
+    pages: BTreeMap<PathBuf, Page>,
+    /// The keys of the pages by their lowercase title and aliases
+    names: HashMap<String, PathBuf>,
+    /// The first line of every block with an `id::` property
+    blocks: HashMap<String, String>,
+    /// The files in `assets/` relative to the graph
+    assets: BTreeSet<PathBuf>,
+}
+
+impl Graph {
+    fn load(graph_dir: &Path, outline: bool) -> anyhow::Result<Self> {
+        let mut graph = Graph::default();
+        for dir in [PAGES_DIR, JOURNALS_DIR, ASSETS_DIR] {
+            // The graph may not have any journals or assets
+            let dir_path = graph_dir.join(dir);
+            if !dir_path.try_exists()? {
+                continue;
+            }
+            for entry in WalkDir::new(dir_path)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_entry(is_visible)
+            {
+                let full_path = entry?.into_path();
+                if !full_path.is_file() {
+                    continue;
+                }
+                let rel_path = full_path.strip_prefix(graph_dir)?.to_path_buf();
+                if dir == ASSETS_DIR {
+                    graph.assets.insert(rel_path);
+                    continue;
+                }
+                if rel_path.extension().is_none_or(|ext| ext != MD_EXT) {
+                    continue;
+                }
+                graph.add_page(&rel_path, full_path, outline)?;
+            }
+        }
+        Ok(graph)
+    }
+
+    fn add_page(
+        &mut self,
+        rel_path: &Path,
+        full_path: PathBuf,
+        outline: bool,
+    ) -> anyhow::Result<()> {
+        let contents = read_to_string(&full_path)
+            .with_context(|| format!("Failed to read file = {}", full_path.display()))?;
+        let stem = strip_ext(Path::new(rel_path.file_name().unwrap_or_default()), MD_EXT);
+        let stem = stem.to_string_lossy();
+        let (outline_properties, blocks) = parse_outline(&contents);
+
+        let mut properties = Mapping::new();
+        let (key, mut title) = match rel_path.starts_with(JOURNALS_DIR) {
+            true => {
+                let (title, date) = journal_title(&stem).unwrap_or((stem.to_string(), None));
+                if let Some(date) = date {
+                    properties.insert("date".into(), date.into());
+                }
+                (Path::new(JOURNALS_DIR).join(stem.as_ref()), title)
+            }
+            false => {
+                // Older graphs URL-encode the '/' of a namespace instead
+                let name = urlencoding::decode(&stem)
+                    .map(|name| name.into_owned())
+                    .unwrap_or_else(|_| stem.to_string())
+                    .replace(NAMESPACE_SEPARATOR, "/");
+                (PathBuf::from(&name), name)
+            }
+        };
+
+        let mut aliases = Vec::new();
+        for (name, value) in outline_properties {
+            match name.as_str() {
+                "title" => title = value,
+                "alias" => aliases.extend(property_list(&value)),
+                "public" => {
+                    properties.insert("publish".into(), property_value(&value));
+                }
+                _ => {
+                    properties.insert(name.into(), property_value(&value));
+                }
+            }
+        }
+
+        // A page is linked to by its file name even when its title:: is different
+        let file_name = key.to_string_lossy().into_owned();
+        for alias in aliases.iter().chain([&title, &file_name]) {
+            self.names.insert(alias.to_lowercase(), key.clone());
+        }
+        for block in &blocks {
+            if let Some(id) = &block.id {
+                let text = block.lines.first().cloned().unwrap_or_default();
+                self.blocks.insert(id.to_lowercase(), text);
+            }
+        }
+        self.pages.insert(
+            key,
+            Page {
+                full_path,
+                title,
+                properties,
+                body: render_outline(&blocks, outline),
+            },
+        );
+        Ok(())
+    }
+
+    /// Rewrite the block references, links and assets of a page into Craft's flavour of
+    /// Markdown, the assets it links to are added to `assets`
+    ///
+    /// Block references to no block of the graph are reported to `diagnostics`. The page's body
+    /// has been flattened so they do not point into the page's file.
+    fn rewrite(
+        &self,
+        page: &Page,
+        name: &str,
+        assets: &mut BTreeSet<PathBuf>,
+        diagnostics: &mut Diagnostics,
+    ) -> String {
+        let body = RE_BLOCK_REF.replace_all(&page.body, |caps: &Captures| {
+            match self.blocks.get(&caps["uuid"].to_lowercase()) {
+                Some(text) => text.clone(),
+                None => {
+                    diagnostics.warning(
+                        Code::BrokenBlockRef,
+                        &page.full_path,
+                        format!(
+                            "the block reference = {} points to no block of the graph, it is left out",
+                            &caps[0]
+                        ),
+                    );
+                    String::new()
+                }
+            }
+        });
+        let body = RE_PAGE_LINK.replace_all(&body, |caps: &Captures| {
+            let (label, name) = match caps.name("labelled") {
+                Some(labelled) => (
+                    caps.name("label")
+                        .map(|m| m.as_str())
+                        .filter(|l| !l.is_empty()),
+                    labelled.as_str(),
+                ),
+                None => (None, &caps["name"]),
+            };
+            match (self.names.get(&name.to_lowercase()), label) {
+                (Some(key), Some(label)) => format!("[[{}|{label}]]", key.display()),
+                (Some(key), None) => format!("[[{}]]", key.display()),
+                (None, label) => label.unwrap_or(name).to_string(),
+            }
+        });
+        RE_ASSET
+            .replace_all(&body, |caps: &Captures| {
+                let decoded = urlencoding::decode(&caps["path"])
+                    .map(|p| p.into_owned())
+                    .unwrap_or_else(|_| caps["path"].to_string());
+                let path_rel = Path::new(ASSETS_DIR).join(decoded);
+                if !self.assets.contains(&path_rel) {
+                    return caps[0].to_string();
+                }
+                let link = asset_link(!caps["bang"].is_empty(), &caps["text"], name, &path_rel);
+                assets.insert(path_rel);
+                link
+            })
+            .into_owned()
+    }
+}
+
+/// A block of a page's outline
+#[derive(Debug, Default, PartialEq)]
+struct Block {
+    /// 0 for the top level bullets
+    depth: usize,
+    /// The block's lines without their indentation and properties
+    lines: Vec<String>,
+    /// The block's `id::` property, the target of a ((block reference))
+    id: Option<String>,
+}
+
+/// Split a page into its page properties and its blocks
+///
+/// The page properties are the `key:: value` lines in front of the first bullet, or the first
+/// block when it holds nothing but properties.
+fn parse_outline(contents: &str) -> (Vec<(String, String)>, Vec<Block>) {
+    let mut properties = Vec::new();
+    let mut blocks: Vec<Block> = Vec::new();
+    // The indentation of the bullets the current block is nested in, with the current block's
+    let mut indents: Vec<&str> = Vec::new();
+    // The opening fence of the code block the current line is in, its lines are never bullets
+    let mut fence: Option<String> = None;
+    for line in contents.lines() {
+        let bullet = match fence {
+            Some(_) => None,
+            None => RE_BULLET.captures(line),
+        };
+        if let Some(caps) = bullet {
+            let indent = caps.name("indent").map_or("", |m| m.as_str());
+            while indents
+                .last()
+                .is_some_and(|last| last.len() >= indent.len())
+            {
+                indents.pop();
+            }
+            indents.push(indent);
+            blocks.push(Block {
+                depth: indents.len() - 1,
+                ..Default::default()
+            });
+            let content = caps.name("content").map_or("", |m| m.as_str());
+            update_fence(&mut fence, content);
+            push_line(&mut blocks, &mut properties, content);
+            continue;
+        }
+        // A continuation line is indented two spaces more than its bullet
+        let text = match indents.last() {
+            Some(indent) => match line.strip_prefix(indent) {
+                Some(rest) => rest.strip_prefix("  ").unwrap_or(rest),
+                None => line.trim_start(),
+            },
+            None => line,
+        };
+        update_fence(&mut fence, text);
+        push_line(&mut blocks, &mut properties, text);
+    }
+
+    // Older graphs write the page properties as the first block
+    if let Some(first) = blocks.first() {
+        let all_properties =
+            !first.lines.is_empty() && first.lines.iter().all(|line| RE_PROPERTY.is_match(line));
+        if properties.is_empty() && first.depth == 0 && all_properties {
+            let first = blocks.remove(0);
+            for line in first.lines {
+                let caps = RE_PROPERTY.captures(&line).unwrap();
+                properties.push((caps["key"].to_string(), caps["value"].trim().to_string()));
+            }
+        }
+    }
+    for block in blocks.iter_mut() {
+        let mut lines = Vec::new();
+        let mut fence = None;
+        for line in std::mem::take(&mut block.lines) {
+            let in_code = fence.is_some();
+            update_fence(&mut fence, &line);
+            if in_code || fence.is_some() {
+                lines.push(line);
+                continue;
+            }
+            match RE_PROPERTY.captures(line.trim()) {
+                Some(caps) if &caps["key"] == "id" => block.id = Some(caps["value"].trim().into()),
+                Some(_) => {}
+                None => lines.push(line),
+            }
+        }
+        block.lines = lines;
+    }
+    blocks.retain(|block| block.lines.iter().any(|line| !line.trim().is_empty()));
+    (properties, blocks)
+}
+
+/// Open or close a code block with a line of a block, `fence` is the opening fence of the code
+/// block the line is in. A block is closed by a fence of the same character at least as long.
+///
+/// Example: "```rust" opens a code block, "```" closes it
+fn update_fence(fence: &mut Option<String>, line: &str) {
+    let trimmed = line.trim_start();
+    let Some(c) = trimmed.chars().next().filter(|c| *c == '`' || *c == '~') else {
+        return;
+    };
+    let marker: String = trimmed.chars().take_while(|m| *m == c).collect();
+    if marker.len() < 3 {
+        return;
+    }
+    match fence {
+        None => *fence = Some(marker),
+        Some(open)
+            if marker.starts_with(open.as_str()) && trimmed[marker.len()..].trim().is_empty() =>
+        {
+            *fence = None
+        }
+        Some(_) => {}
+    }
+}
+
+/// Add a line to the last block, lines in front of the first bullet are page properties
+fn push_line(blocks: &mut [Block], properties: &mut Vec<(String, String)>, line: &str) {
+    match blocks.last_mut() {
+        Some(block) => block.lines.push(line.to_string()),
+        None => {
+            if let Some(caps) = RE_PROPERTY.captures(line.trim()) {
+                properties.push((caps["key"].to_string(), caps["value"].trim().to_string()));
+            }
+        }
+    }
+}
+
+/// The body of a page, every block a paragraph of its own or the outline as nested lists
+fn render_outline(blocks: &[Block], outline: bool) -> String {
+    let mut body = String::new();
+    for block in blocks {
+        if !outline {
+            body.push_str(block.lines.join("\n").trim_end());
+            body.push_str("\n\n");
+            continue;
+        }
+        let indent = "  ".repeat(block.depth);
+        for (i, line) in block.lines.iter().enumerate() {
+            let marker = if i == 0 { "- " } else { "  " };
+            match line.is_empty() {
+                true => body.push('\n'),
+                false => body.push_str(&format!("{indent}{marker}{line}\n")),
+            }
+        }
+    }
+    body
+}
+
+/// The values of a property holding a list
+///
+/// Example: "[[Dovetails]], Dovetail Joints" -> ["Dovetails", "Dovetail Joints"]
+fn property_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|v| v.trim().trim_start_matches("[[").trim_end_matches("]]"))
+        .filter(|v| !v.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn property_value(value: &str) -> Value {
+    match value {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        value => Value::String(value.into()),
+    }
+}
+
+/// The title Logseq gives a journal by default (`MMM do, yyyy`) and its date
+///
+/// Example: "2023_01_04" -> ("Jan 4th, 2023", "2023-01-04")
+fn journal_title(stem: &str) -> Option<(String, Option<String>)> {
+    let date = Date::parse(stem, format_description!("[year]_[month]_[day]")).ok()?;
+    let day = date.day();
+    let suffix = match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    let month = date.month().to_string();
+    let title = format!("{} {day}{suffix}, {}", &month[..3], date.year());
+    let iso = format!(
+        "{}-{:02}-{:02}",
+        date.year(),
+        u8::from(date.month()),
+        date.day()
+    );
+    Some((title, Some(iso)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::TempDir;
+
+    #[test]
+    fn pages_are_flattened_with_links_and_block_refs() {
+        let graph_dir = TempDir::new("graph");
+        graph_dir.write("assets/plan_1680.png", "");
+        graph_dir.write(
+            "pages/Woodworking___Dovetail Joint.md",
+            "alias:: Dovetails\n\
+            created:: 2021-03-14\n\
+            \n\
+            - Cut the tails first\n\
+            \tid:: 6439a1f2-0000-4000-8000-000000000001\n\
+            \t- ![plan.png](../assets/plan_1680.png){:height 100}\n\
+            \t  with the pins second\n",
+        );
+        graph_dir.write(
+            "journals/2023_01_04.md",
+            "- Read about [[dovetails]], [the joint]([[Dovetails]]) and [[Saws]]\n\
+            \t- ((6439a1f2-0000-4000-8000-000000000001))\n",
+        );
+        graph_dir.write(
+            "pages/Scraps.md",
+            "- See ((6439a1f2-0000-4000-8000-00000000dead))\n",
+        );
+
+        let scan = Logseq::default()
+            .read(&Space::new(graph_dir.to_path_buf()), &Options::default())
+            .unwrap();
+        let note = |key: &str| {
+            scan.files
+                .iter()
+                .find(|f| f.path_rel == Path::new(key))
+                .unwrap()
+        };
+
+        let joint = note("Woodworking/Dovetail Joint");
+        assert_eq!(joint.name, "Dovetail Joint");
+        assert_eq!(
+            joint.body,
+            "---\ncreated: 2021-03-14\n---\n\
+            # Dovetail Joint\n\
+            Cut the tails first\n\n\
+            ![plan.png](Dovetail%20Joint.assets/assets/plan_1680.png)\nwith the pins second\n\n"
+        );
+        assert!(joint
+            .find_asset(Path::new("assets/plan_1680.png"))
+            .is_some());
+
+        let journal = note("journals/2023_01_04");
+        assert_eq!(journal.name, "Jan 4th, 2023");
+        assert_eq!(
+            journal.body,
+            "---\ndate: 2023-01-04\n---\n\
+            # Jan 4th, 2023\n\
+            Read about [[Woodworking/Dovetail Joint]], [[Woodworking/Dovetail Joint|the joint]] and Saws\n\n\
+            Cut the tails first\n\n"
+        );
+        let [dangling] = &scan.diagnostics.iter().collect::<Vec<_>>()[..] else {
+            panic!("expected a single diagnostic");
+        };
+        assert_eq!(dangling.code, Code::BrokenBlockRef);
+        assert_eq!(dangling.file, graph_dir.join("pages/Scraps.md"));
+        assert_eq!(dangling.span, None);
+
+        let outline = Logseq { outline: true }
+            .read(&Space::new(graph_dir.to_path_buf()), &Options::default())
+            .unwrap();
+        let journal = outline
+            .files
+            .iter()
+            .find(|f| f.path_rel == Path::new("journals/2023_01_04"))
+            .unwrap();
+        assert!(journal.body.ends_with(
+            "- Read about [[Woodworking/Dovetail Joint]], [[Woodworking/Dovetail Joint|the joint]] and Saws\n  - Cut the tails first\n"
+        ));
+    }
+
+    #[test]
+    fn fenced_lines_belong_to_their_block() {
+        // A graph without journals or assets
+        let graph_dir = TempDir::new("graph-fences");
+        graph_dir.write(
+            "pages/Deploy.md",
+            "- Write the workflow\n\
+            \t- ```yaml\n\
+            \t  - name: build\n\
+            \t    id:: not-a-property\n\
+            \t  ```\n\
+            \t  then push it\n\
+            - Done\n",
+        );
+
+        let scan = Logseq::default()
+            .read(&Space::new(graph_dir.to_path_buf()), &Options::default())
+            .unwrap();
+        assert_eq!(scan.files.len(), 1);
+        assert_eq!(
+            scan.files[0].body,
+            "# Deploy\n\
+            Write the workflow\n\n\
+            ```yaml\n\
+            - name: build\n  \
+            id:: not-a-property\n\
+            ```\n\
+            then push it\n\n\
+            Done\n\n"
+        );
+    }
+}
@@ -1,15 +1,9 @@
-#[macro_use]
-extern crate lazy_static;
-
 use clap::Parser;
-use craft_files::CraftDocs;
-use slug::slugify;
-use std::path::{Path, PathBuf};
-
-use crate::zola_files::ZolaFiles;
-
-mod craft_files;
-mod zola_files;
+use craft_zola::{
+    CollisionStrategy, Converter, DateSource, DiagnosticFormat, InputFormat, Options, Pipeline,
+    SlugStrategy, Space, Target, ZolaConfig,
+};
+use std::path::{PathBuf, MAIN_SEPARATOR};
 
 /// C2Z is a simple program to parse Craft exported Markdown files and convert them into Zola
 /// compatible markdown files
@@ -18,28 +12,183 @@ mod zola_files;
 struct Cli {
     /// Input directory is a path to Craft's exported markdown directory
     ///
-    /// This directory's name will be used when created a sub directory
-    /// inside Zola's /content dir
-    #[arg(short, long)]
-    input: PathBuf,
+    /// Craft's `.zip` export can be given as is, it is read without extracting it.
+    ///
+    /// Repeat it to convert several Craft spaces at once, every space becomes its own section
+    /// inside OUTPUT and [[wiki links]] resolve across spaces. A space is named after its
+    /// directory unless given as NAME=PATH, links can name it with [[NAME:Note]].
+    ///
+    /// Example: -i Engineering=~/Exports/Work -i Reading=~/Exports/Books
+    #[arg(short, long, required = true, value_name = "[NAME=]PATH", value_parser = parse_space)]
+    input: Vec<Space>,
 
-    /// Output directory is a path to the Zola `content/` directory
+    /// Output directory is a path inside the site's `content/` directory (Zola or Hugo)
     ///
     /// TODO: What to do if there is already a directory present which matches the input
     /// directory's name? Write over? Probably.
     #[arg(short, long)]
     output: PathBuf,
+
+    /// The path of OUTPUT inside the Zola `content/` directory, internal links start with it
+    ///
+    /// Inferred from OUTPUT when it is inside a `content/` directory, in which case the two have
+    /// to match. Otherwise it defaults to the name of OUTPUT.
+    ///
+    /// Example: --section notes/garden -> [TLS](@/notes/garden/tls.md)
+    #[arg(long)]
+    section: Option<String>,
+
+    /// The static site generator the notes are written for
+    #[arg(long, value_enum, default_value_t)]
+    target: Target,
+
+    /// The format of the INPUT directories
+    #[arg(long, value_enum, default_value_t)]
+    from: InputFormat,
+
+    /// Slugify the file names of assets (images, PDFs, ...) when copying them next to their note
+    ///
+    /// Links to the assets are rewritten to the new names
+    #[arg(long)]
+    slugify_assets: bool,
+
+    /// Leave assets which are never linked to from their note out of the site
+    ///
+    /// They are reported as warnings either way
+    #[arg(long)]
+    prune_unreferenced_assets: bool,
+
+    /// What to do when two pages, sections or assets map to the same slug
+    #[arg(long, value_enum, default_value_t)]
+    on_collision: CollisionStrategy,
+
+    /// How warnings and errors about the notes are printed
+    ///
+    /// "json" prints one object per line on stdout, with a `code` to filter them by
+    #[arg(long, value_enum, default_value_t)]
+    format: DiagnosticFormat,
+
+    /// How file and directory names are slugified [default: Zola's `slugify.paths` or "on"]
+    #[arg(long, value_enum)]
+    slugify_paths: Option<SlugStrategy>,
+
+    /// How the `#header` of a [[wiki link]] is slugified [default: Zola's `slugify.anchors` or
+    /// "on"]
+    #[arg(long, value_enum)]
+    slugify_anchors: Option<SlugStrategy>,
+
+    /// Path to the Zola site's `config.toml` to read the `[slugify]` settings from
+    ///
+    /// When omitted it is looked up next to the `content/` directory OUTPUT is part of
+    #[arg(long)]
+    zola_config: Option<PathBuf>,
+
+    /// Where the `date` and `updated` of a note come from, in order of priority
+    ///
+    /// The first source which knows a date wins
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = DateSource::DEFAULT_ORDER
+    )]
+    dates: Vec<DateSource>,
+
+    /// Only convert notes matching this gitignore-style pattern, can be repeated
+    ///
+    /// Example: --include "Engineering/" --include "Reading/*.md"
+    #[arg(long)]
+    include: Vec<String>,
+
+    /// Leave out notes matching this gitignore-style pattern, can be repeated
+    ///
+    /// Patterns from a `.craftzolaignore` file in the root of INPUT are applied as well. Links to
+    /// left out notes become plain text.
+    ///
+    /// Example: --exclude "Inbox/" --exclude "Templates/"
+    #[arg(long)]
+    exclude: Vec<String>,
+
+    /// The transforms every note's body goes through, in order [default: Zola's
+    /// `extra.craft_zola.transforms` or "wiki-links,day-links,asset-links,code-fences"]
+    ///
+    /// Transforms which are not listed are disabled
+    #[arg(long, value_delimiter = ',')]
+    transforms: Option<Vec<String>>,
+
+    /// Leave a transform out of the pipeline, can be repeated
+    ///
+    /// Added to Zola's `extra.craft_zola.disable_transforms`
+    #[arg(long)]
+    disable_transform: Vec<String>,
+}
+
+/// Parse an --input argument given as PATH or NAME=PATH
+fn parse_space(arg: &str) -> Result<Space, String> {
+    if let Some((name, path)) = arg.split_once('=') {
+        // A directory which happens to contain a '=' is still a path
+        if !name.is_empty() && !name.contains(MAIN_SEPARATOR) && !PathBuf::from(arg).exists() {
+            return Ok(Space::named(name.into(), path.into()));
+        }
+    }
+    Ok(Space::new(arg.into()))
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
-    let input_dir = cli.input;
+    let spaces = cli.input;
     let output_dir = cli.output;
-    let mut zola = ZolaFiles::new(output_dir);
-    let mut craft = CraftDocs::new(input_dir);
-    craft.process_files()?;
-    craft.format_markdown()?;
-    zola.write_files(craft)?;
+    let zola_config = match cli.zola_config.or_else(|| ZolaConfig::find(&output_dir)) {
+        Some(config_path) => ZolaConfig::read(&config_path)?,
+        None => ZolaConfig::default(),
+    };
+    let mut options = Options::default()
+        .slugify_assets(cli.slugify_assets)
+        .prune_unreferenced_assets(cli.prune_unreferenced_assets)
+        .on_collision(cli.on_collision)
+        .diagnostic_format(cli.format)
+        .slugify_paths(
+            cli.slugify_paths
+                .or(zola_config.slugify.paths)
+                .unwrap_or_default(),
+        )
+        .slugify_anchors(
+            cli.slugify_anchors
+                .or(zola_config.slugify.anchors)
+                .unwrap_or_default(),
+        )
+        .date_sources(cli.dates)
+        .target(cli.target)
+        .input_format(cli.from);
+
+    let mut transforms = Pipeline::default();
+    let craft_zola_config = zola_config.extra.craft_zola;
+    if let Some(order) = cli.transforms.or(craft_zola_config.transforms) {
+        transforms.set_order(&order)?;
+    }
+    for name in craft_zola_config
+        .disable_transforms
+        .iter()
+        .chain(&cli.disable_transform)
+    {
+        transforms.disable(name)?;
+    }
+    options = options.transforms(transforms);
+    for pattern in cli.include {
+        options = options.include(pattern);
+    }
+    for pattern in cli.exclude {
+        options = options.exclude(pattern);
+    }
+
+    let mut converter = Converter::new(output_dir).options(options);
+    for space in spaces {
+        converter = converter.space(space);
+    }
+    if let Some(section) = cli.section {
+        converter = converter.section(section);
+    }
+    converter.run()?;
 
     // fs::create_dir_all("/Users/CASE/Downloads/my-new-directory/nested-one/nested-two")?;
     // fs::write(
@@ -0,0 +1,310 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::{
+    backend::{self, is_bundle, link_destination, Backend, Written, DIR_EMOJI},
+    craft_files::{strip_ext, CraftDocs, FileData, MD_EXT},
+};
+
+const SUMMARY_FILE: &str = "SUMMARY.md";
+const README_FILE: &str = "README.md";
+
+/// mdBook, chosen with `--target mdbook`
+///
+/// OUTPUT_DIR is the book's `src/` directory. Every note becomes a chapter following the folder
+/// hierarchy of the export and every folder a chapter of its own (`README.md`) holding the
+/// chapters inside it. `SUMMARY.md` lists the chapters of a folder ordered by NoteType weight,
+/// then title, followed by its sub folders.
+///
+/// Links between notes are relative `.md` links and assets are copied next to their chapter. mdBook
+/// has no frontmatter, so dates and aliases are left out and drafts are listed as draft chapters.
+///
+/// Example link: [Dovetail Joint](../woodworking/dovetail-joint/index.md#marking-out)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MdBook;
+
+impl Backend for MdBook {
+    fn name(&self) -> &str {
+        "mdbook"
+    }
+
+    fn content_dir(&self) -> &Path {
+        Path::new("src")
+    }
+
+    /// Example:
+    ///     "woodworking/dovetail-joint/index.md" -> "/woodworking/dovetail-joint/index.html"
+    fn page_url(&self, mount: &Path, note: &FileData) -> String {
+        let page = strip_ext(&note.path_slug, MD_EXT).with_extension("html");
+        format!("/{}", mount.join(page).display())
+    }
+
+    fn link(
+        &self,
+        _mount: &str,
+        from: &FileData,
+        to: &FileData,
+        text: &str,
+        header: Option<&str>,
+    ) -> String {
+        let from_dir = from.path_slug.parent().unwrap_or(Path::new(""));
+        let destination = format!(
+            "{}{}",
+            relative_path(from_dir, &to.path_slug).display(),
+            header.unwrap_or_default()
+        );
+        format!("[{text}]({})", link_destination(&destination))
+    }
+
+    fn frontmatter(&self, note: &FileData) -> String {
+        // The title Craft exported as the first H1 was removed, mdBook needs it back
+        format!("# {}\n", note.name)
+    }
+
+    fn section_index_name(&self) -> Option<&str> {
+        Some(README_FILE)
+    }
+
+    fn section_index(&self, title: &str, _root: bool) -> String {
+        format!("# {title}\n")
+    }
+
+    fn write_files(&self, craft_docs: &CraftDocs, output_dir: &Path) -> anyhow::Result<Written> {
+        let mut written = backend::write_files(self, craft_docs, output_dir)?;
+        let book = Folder::from_notes(craft_docs.files.values());
+
+        // Folders which only hold page bundles did not get a README.md from write_files
+        for (dir_slug, title) in book.folders_flat() {
+            let readme = dir_slug.join(README_FILE);
+            let readme_path = output_dir.join(&readme);
+            if readme_path.try_exists()? {
+                continue;
+            }
+            fs::create_dir_all(output_dir.join(&dir_slug))?;
+            fs::write(&readme_path, self.section_index(&title, false)).with_context(|| {
+                format!(
+                    "Failed to write a section index file at path = {}",
+                    readme_path.display()
+                )
+            })?;
+            written.sections.push(readme);
+        }
+
+        let summary_path = output_dir.join(SUMMARY_FILE);
+        fs::write(&summary_path, book.summary()).with_context(|| {
+            format!(
+                "Failed to write the book's summary at path = {}",
+                summary_path.display()
+            )
+        })?;
+        written.sections.push(PathBuf::from(SUMMARY_FILE));
+        written.sections.sort();
+        Ok(written)
+    }
+}
+
+/// A folder of the export, a chapter holding the chapters of its notes and sub folders
+#[derive(Debug, Default)]
+struct Folder<'a> {
+    title: String,
+    /// The folder's path relative to OUTPUT_DIR
+    dir_slug: PathBuf,
+    notes: Vec<&'a FileData>,
+    /// Keyed by the folder's name in the export, so they are ordered by title
+    folders: BTreeMap<String, Folder<'a>>,
+}
+
+impl<'a> Folder<'a> {
+    fn from_notes(notes: impl Iterator<Item = &'a FileData>) -> Self {
+        let mut root = Folder {
+            title: format!("{DIR_EMOJI} Garden"),
+            ..Default::default()
+        };
+        for note in notes {
+            // The directory holding the chapter, a page bundle is a directory of its own
+            let mut page_dir = note.path_slug.clone();
+            page_dir.pop();
+            if is_bundle(&note.path_slug) {
+                page_dir.pop();
+            }
+            // Every folder of path_rel has a slugified counterpart in page_dir, the index note of
+            // a section is the README of its own folder
+            let folder_rel = match note.section {
+                true => note.path_rel.as_path(),
+                false => note.path_rel.parent().unwrap_or(Path::new("")),
+            };
+            let names = folder_rel.components();
+            let mut folder = &mut root;
+            let mut dir_slug = PathBuf::new();
+            for (name, slug) in names.zip(page_dir.components()) {
+                dir_slug.push(slug);
+                let name = name.as_os_str().to_string_lossy().into_owned();
+                folder = folder
+                    .folders
+                    .entry(name.clone())
+                    .or_insert_with(|| Folder {
+                        title: format!("{DIR_EMOJI} {name}"),
+                        dir_slug: dir_slug.clone(),
+                        ..Default::default()
+                    });
+            }
+            match note.section {
+                true => folder.title.clone_from(&note.name),
+                false => folder.notes.push(note),
+            }
+        }
+        root
+    }
+
+    /// Every folder below this one as (dir_slug, title)
+    fn folders_flat(&self) -> Vec<(PathBuf, String)> {
+        self.folders
+            .values()
+            .flat_map(|folder| {
+                let mut flat = vec![(folder.dir_slug.clone(), folder.title.clone())];
+                flat.extend(folder.folders_flat());
+                flat
+            })
+            .collect()
+    }
+
+    /// The contents of SUMMARY.md
+    ///
+    /// Example:
+    /// ```markdown
+    /// # Summary
+    ///
+    /// [🌳 Garden](README.md)
+    ///
+    /// - [🌳 🌲 Woodworking](woodworking/README.md)
+    ///     - [Dovetail Joint](woodworking/dovetail-joint/index.md)
+    /// ```
+    fn summary(&self) -> String {
+        let mut summary = format!("# Summary\n\n[{}](README.md)\n\n", self.title);
+        self.render(0, &mut summary);
+        summary
+    }
+
+    fn render(&self, depth: usize, out: &mut String) {
+        let indent = "    ".repeat(depth);
+        let mut notes = self.notes.clone();
+        notes.sort_by(|a, b| {
+            (a.note_type.to_weight(), &a.name).cmp(&(b.note_type.to_weight(), &b.name))
+        });
+        for note in notes {
+            // Drafts are listed without a link, mdBook renders them greyed out
+            let destination = match note.draft {
+                true => String::new(),
+                false => summary_destination(&note.path_slug),
+            };
+            out.push_str(&format!("{indent}- [{}]({destination})\n", note.name));
+        }
+        for folder in self.folders.values() {
+            let readme = folder.dir_slug.join(README_FILE);
+            out.push_str(&format!(
+                "{indent}- [{}]({})\n",
+                folder.title,
+                summary_destination(&readme)
+            ));
+            folder.render(depth + 1, out);
+        }
+    }
+}
+
+fn summary_destination(path: &Path) -> String {
+    link_destination(&path.display().to_string()).into_owned()
+}
+
+/// The path of `to` relative to the directory `from_dir`, both relative to OUTPUT_DIR
+///
+/// Example:
+///     "finance", "woodworking/hand-tools.md" -> "../woodworking/hand-tools.md"
+fn relative_path(from_dir: &Path, to: &Path) -> PathBuf {
+    let from: Vec<Component> = from_dir.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut relative = PathBuf::new();
+    for _ in common..from.len() {
+        relative.push("..");
+    }
+    relative.extend(&to[common..]);
+    relative
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::craft_files::NoteType;
+
+    fn note(path_rel: &str, path_slug: &str, note_type: NoteType) -> FileData {
+        let path_rel = PathBuf::from(path_rel);
+        FileData {
+            name: path_rel.file_name().unwrap().to_string_lossy().into(),
+            path_rel,
+            path_slug: PathBuf::from(path_slug),
+            note_type,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn summary_orders_chapters_by_weight_then_title() {
+        let notes = [
+            note(
+                "Woodworking/Sanding",
+                "woodworking/sanding.md",
+                NoteType::None,
+            ),
+            note(
+                "Woodworking/Dovetail Joint",
+                "woodworking/dovetail-joint/index.md",
+                NoteType::Evergreen,
+            ),
+            note(
+                "Woodworking/Chisels",
+                "woodworking/chisels.md",
+                NoteType::None,
+            ),
+            note(
+                "Woodworking/Finishes/Oil",
+                "woodworking/finishes/oil.md",
+                NoteType::Potted,
+            ),
+        ];
+        let book = Folder::from_notes(notes.iter());
+        assert_eq!(
+            book.summary(),
+            "# Summary\n\n\
+            [🌳 Garden](README.md)\n\n\
+            - [🌳 Woodworking](woodworking/README.md)\n    \
+                - [Dovetail Joint](woodworking/dovetail-joint/index.md)\n    \
+                - [Chisels](woodworking/chisels.md)\n    \
+                - [Sanding](woodworking/sanding.md)\n    \
+                - [🌳 Finishes](woodworking/finishes/README.md)\n        \
+                    - [Oil](woodworking/finishes/oil.md)\n"
+        );
+    }
+
+    #[test]
+    fn links_are_relative_to_the_chapter() {
+        let from = note("Finance/Taxes", "finance/taxes.md", NoteType::None);
+        let to = note(
+            "Woodworking/Dovetail Joint",
+            "woodworking/dovetail-joint/index.md",
+            NoteType::None,
+        );
+        assert_eq!(
+            MdBook.link("", &from, &to, &to.name, Some("#marking-out")),
+            "[Dovetail Joint](../woodworking/dovetail-joint/index.md#marking-out)"
+        );
+        assert_eq!(
+            MdBook.link("", &to, &from, &from.name, None),
+            "[Taxes](../../finance/taxes.md)"
+        );
+    }
+}
@@ -0,0 +1,407 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use regex::{Captures, Regex};
+use walkdir::WalkDir;
+
+use crate::{
+    craft_files::{strip_ext, Asset, FileData, Options, Space, MD_EXT},
+    reader::{asset_link, is_visible, normalize, Reader, Scan},
+    transforms::{Code, Diagnostics},
+};
+
+lazy_static! {
+    // The ID Notion puts at the end of the name of every page and database
+    // Names the capture group "name" for the name without it
+    //
+    // Example: "Dovetail Joint 9f8e7d6c5b4a39281706f5e4d3c2b1a0"
+    static ref RE_NOTION_ID: Regex = Regex::new(r"^(?<name>.*?)\s*[0-9a-f]{32}$").unwrap();
+    // A relative link to a page, a database or a file of the export, URL-encoded
+    // Names the capture group "bang" for the optional leading '!' of an image, "text" for the link
+    // text and "path" for the destination. Destinations with a ':' are URLs and never match.
+    //
+    // Example: [Dovetail Joint](Woodworking%201a2b/Dovetail%20Joint%209f8e.md)
+    static ref RE_RELATIVE_LINK: Regex =
+        Regex::new(r"(?<bang>!?)\[(?<text>[^\[\]]*)\]\((?<path>[^()\s:]+)\)").unwrap();
+}
+
+const CSV_EXT: &str = "csv";
+/// Newer exports write every database twice, once with only the rows of its default view and once
+/// with all of them
+const ALL_ROWS_SUFFIX: &str = "_all";
+
+/// Notion's Markdown & CSV export, chosen with `--from notion`
+///
+/// The IDs Notion appends to file and folder names are stripped from the notes' paths, the
+/// relative links between pages still name them and are resolved to the notes. A page with sub
+/// pages becomes the index of their section, a database (`.csv`) becomes a section listing its
+/// rows as a table.
+///
+/// Files a page links to are copied next to it when they are in the page's own folder.
+///
+/// Example:
+///     "Woodworking 1a2b…/Dovetail Joint 9f8e….md" -> "Woodworking/Dovetail Joint"
+///     "[Joint](Woodworking%201a2b…/Dovetail%20Joint%209f8e….md)" -> "[[Woodworking/Dovetail Joint|Joint]]"
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Notion;
+
+impl Reader for Notion {
+    fn name(&self) -> &str {
+        "notion"
+    }
+
+    fn read(&self, space: &Space, options: &Options) -> anyhow::Result<Scan> {
+        let export = Export::load(&space.input_dir)?;
+        let slugify_name = options.slugify_assets.then_some(options.slugify_paths);
+        let mut files: BTreeMap<PathBuf, FileData> = BTreeMap::new();
+        let mut diagnostics = Diagnostics::default();
+        for page in &export.pages {
+            let full_path = space.input_dir.join(page);
+            let key = &export.keys[&strip_ext(page, MD_EXT)];
+            let mut file_data = FileData::try_from(full_path.clone())?;
+            file_data.set_key(key, &space.prefix, options.slugify_paths);
+            file_data.name = key.file_name().unwrap_or_default().to_string_lossy().into();
+            // The page's sub pages and files are in the folder of the same name
+            let page_dir = strip_ext(page, MD_EXT);
+            file_data.section = export.note_dirs.contains(&page_dir);
+
+            let body = read_to_string(&full_path)
+                .with_context(|| format!("Failed to read file = {}", full_path.display()))?;
+            let mut assets = BTreeSet::new();
+            file_data.body =
+                export.rewrite_links(page, &file_data.name, &body, &mut assets, &mut diagnostics);
+            if !assets.is_empty() {
+                file_data.assets = Some(
+                    assets
+                        .into_iter()
+                        .map(|path_rel| Asset::new(path_rel, slugify_name))
+                        .collect(),
+                );
+                file_data.assets_dir = Some(space.input_dir.join(page_dir));
+                let page_slug = strip_ext(&file_data.path_slug, MD_EXT);
+                file_data.path_slug = options.backend.page_path(&page_slug, true);
+            }
+            files.insert(key.clone(), file_data);
+        }
+
+        for (key, database) in &export.databases {
+            let full_path = space.input_dir.join(database);
+            let listing = export.listing(database, &full_path)?;
+            let file_data = match files.get_mut(key) {
+                Some(file_data) => file_data,
+                // The database is a page of its own
+                None => {
+                    let mut file_data = FileData::try_from(full_path.clone())?;
+                    file_data.set_key(key, &space.prefix, options.slugify_paths);
+                    file_data.name = key.file_name().unwrap_or_default().to_string_lossy().into();
+                    file_data.body = format!("# {}\n", file_data.name);
+                    files.entry(key.clone()).or_insert(file_data)
+                }
+            };
+            file_data.section = true;
+            file_data.body = format!("{}\n\n{listing}", file_data.body.trim_end());
+        }
+
+        Ok(Scan {
+            files: files.into_values().collect(),
+            directories: export
+                .note_dirs
+                .iter()
+                .map(|dir| space.prefix.join(&export.keys[dir]))
+                .collect(),
+            diagnostics,
+            ..Default::default()
+        })
+    }
+}
+
+/// Every page, database and file of an export, relative to the export
+#[derive(Debug, Default)]
+struct Export {
+    export_dir: PathBuf,
+    pages: Vec<PathBuf>,
+    /// The `.csv` of every database by the database's key
+    databases: BTreeMap<PathBuf, PathBuf>,
+    /// Every file which is neither a page nor a database
+    files: HashSet<PathBuf>,
+    /// The folders holding pages or databases
+    note_dirs: BTreeSet<PathBuf>,
+    /// The key of every page, database and folder by its path in the export without the
+    /// extension
+    keys: HashMap<PathBuf, PathBuf>,
+    /// Every key given out so far, to keep pages with the same name apart
+    taken: HashSet<PathBuf>,
+}
+
+impl Export {
+    fn load(export_dir: &Path) -> anyhow::Result<Self> {
+        let mut export = Export {
+            export_dir: export_dir.to_path_buf(),
+            ..Default::default()
+        };
+        let mut csvs = Vec::new();
+        for entry in WalkDir::new(export_dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(is_visible)
+        {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let rel_path = entry.path().strip_prefix(export_dir)?.to_path_buf();
+            let parent = rel_path.parent().unwrap_or(Path::new("")).to_path_buf();
+            match rel_path.extension().and_then(|ext| ext.to_str()) {
+                Some(MD_EXT) => {
+                    export.note_dirs.insert(parent);
+                    export.pages.push(rel_path);
+                }
+                Some(CSV_EXT) => {
+                    export.note_dirs.insert(parent);
+                    csvs.push(rel_path);
+                }
+                _ => {
+                    export.files.insert(rel_path);
+                }
+            }
+        }
+        export.note_dirs.remove(Path::new(""));
+
+        for page in export.pages.clone() {
+            export.key(&strip_ext(&page, MD_EXT));
+        }
+        for csv in csvs {
+            let database = database_path(&csv);
+            let key = export.key(&database);
+            // The `_all.csv` sorts after the default view's and replaces it
+            export.databases.insert(key, csv);
+        }
+        for dir in export.note_dirs.clone() {
+            export.key(&dir);
+        }
+        Ok(export)
+    }
+
+    /// The key of a page, database or folder from its path in the export without the extension
+    ///
+    /// A page and the folder of its sub pages share their key. When two pages in the same folder
+    /// have the same name, the second keeps its ID.
+    ///
+    /// Example: "Woodworking 1a2b…/Dovetail Joint 9f8e…" -> "Woodworking/Dovetail Joint"
+    fn key(&mut self, path: &Path) -> PathBuf {
+        if let Some(key) = self.keys.get(path) {
+            return key.clone();
+        }
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => self.key(parent),
+            _ => PathBuf::new(),
+        };
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut key = parent.join(strip_id(&name));
+        if self.taken.contains(&key) {
+            key = parent.join(name.as_ref());
+        }
+        self.taken.insert(key.clone());
+        self.keys.insert(path.to_path_buf(), key.clone());
+        key
+    }
+
+    /// Rewrite the relative links of the page at `page` into Craft's flavour of Markdown, the
+    /// files in the page's own folder it links to are added to `assets` relative to that folder
+    ///
+    /// Links to nothing the page can link to are reported to `diagnostics`
+    fn rewrite_links(
+        &self,
+        page: &Path,
+        name: &str,
+        body: &str,
+        assets: &mut BTreeSet<PathBuf>,
+        diagnostics: &mut Diagnostics,
+    ) -> String {
+        let page_dir = strip_ext(page, MD_EXT);
+        let from_dir = page.parent().unwrap_or(Path::new(""));
+        RE_RELATIVE_LINK
+            .replace_all(body, |caps: &Captures| {
+                let path = caps["path"].split('#').next().unwrap_or_default();
+                let Ok(decoded) = urlencoding::decode(path) else {
+                    return caps[0].to_string();
+                };
+                let target = normalize(&from_dir.join(decoded.as_ref()));
+                let extension = target.extension().and_then(|ext| ext.to_str());
+
+                let key = match extension {
+                    Some(MD_EXT) => self.keys.get(&strip_ext(&target, MD_EXT)),
+                    Some(CSV_EXT) => self.keys.get(&database_path(&target)),
+                    _ => None,
+                };
+                // The text of the link is kept unless it is empty or only the page's title
+                if let Some(key) = key {
+                    let text = &caps["text"];
+                    return match text.is_empty() || key.file_name().is_some_and(|name| name == text) {
+                        true => format!("[[{}]]", key.display()),
+                        false => format!("[[{}|{text}]]", key.display()),
+                    };
+                }
+                if self.files.contains(&target) {
+                    if let Ok(path_rel) = target.strip_prefix(&page_dir) {
+                        let link = asset_link(!caps["bang"].is_empty(), &caps["text"], name, path_rel);
+                        assets.insert(path_rel.to_path_buf());
+                        return link;
+                    }
+                }
+                let link = caps.get(0).unwrap();
+                diagnostics
+                    .warning(
+                        Code::UnresolvedLink,
+                        &self.export_dir.join(page),
+                        format!(
+                            "the link = {} points to nothing the page can link to, it is left as is",
+                            link.as_str()
+                        ),
+                    )
+                    .at_range(body, link.range());
+                caps[0].to_string()
+            })
+            .into_owned()
+    }
+
+    /// The rows of a database as a Markdown table, the first column (the row's name) links to the
+    /// row's page
+    fn listing(&self, database: &Path, full_path: &Path) -> anyhow::Result<String> {
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_path(full_path)
+            .with_context(|| format!("Failed to read the database = {}", full_path.display()))?;
+        let header: Vec<String> = reader.headers()?.iter().map(table_cell).collect();
+        let mut listing = format!(
+            "| {} |\n|{}\n",
+            header.join(" | "),
+            " --- |".repeat(header.len())
+        );
+
+        // The pages of the rows are in the folder of the same name
+        let rows_dir = &self.keys[&database_path(database)];
+        for record in reader.records() {
+            let record = record.with_context(|| {
+                format!("Invalid row in the database = {}", full_path.display())
+            })?;
+            let cells: Vec<String> = record
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| {
+                    let row = rows_dir.join(cell);
+                    match i == 0 && self.taken.contains(&row) {
+                        true => format!("[[{}]]", row.display()),
+                        false => table_cell(cell),
+                    }
+                })
+                .collect();
+            listing.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+        Ok(listing)
+    }
+}
+
+/// The name of a page, database or folder without its ID
+///
+/// Example: "Dovetail Joint 9f8e7d6c5b4a39281706f5e4d3c2b1a0" -> "Dovetail Joint"
+fn strip_id(name: &str) -> String {
+    match RE_NOTION_ID.captures(name) {
+        Some(caps) if !caps["name"].is_empty() => caps["name"].to_string(),
+        _ => name.to_string(),
+    }
+}
+
+/// The path of a database without the extension, the same as the folder of its rows
+///
+/// Example: "Projects 5e6f…_all.csv" -> "Projects 5e6f…"
+fn database_path(csv: &Path) -> PathBuf {
+    let database = strip_ext(csv, CSV_EXT);
+    let name = database.file_name().unwrap_or_default().to_string_lossy();
+    match name.strip_suffix(ALL_ROWS_SUFFIX) {
+        Some(name) => database.with_file_name(name),
+        None => database,
+    }
+}
+
+/// A cell of a Markdown table, on a single line and without unescaped '|'
+fn table_cell(cell: &str) -> String {
+    cell.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::TempDir;
+
+    #[test]
+    fn ids_are_stripped_and_links_resolved() {
+        let export_dir = TempDir::new("notion");
+        let woodworking = "Woodworking 1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d";
+        let joint = "Dovetail Joint 9f8e7d6c5b4a39281706f5e4d3c2b1a0";
+        let projects = "Projects 5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b";
+        let saw = "Table Saw 0a1b2c3d4e5f60718293a4b5c6d7e8f9";
+        export_dir.write(
+            &format!("{woodworking}.md"),
+            "# Woodworking\n\n\
+            [the joint](Woodworking%201a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d/Dovetail%20Joint%209f8e7d6c5b4a39281706f5e4d3c2b1a0.md)\n",
+        );
+        export_dir.write(
+            &format!("{woodworking}/{joint}.md"),
+            "# Dovetail Joint\n\n\
+            ![Untitled.png](Dovetail%20Joint%209f8e7d6c5b4a39281706f5e4d3c2b1a0/Untitled.png)\n\
+            [Projects](../Projects%205e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b.csv)\n",
+        );
+        export_dir.write(&format!("{woodworking}/{joint}/Untitled.png"), "");
+        export_dir.write(
+            &format!("{projects}.csv"),
+            "Name,Status\nTable Saw,Done\nRouter,\"To | do\"\n",
+        );
+        export_dir.write(&format!("{projects}/{saw}.md"), "# Table Saw\n");
+
+        let scan = Notion
+            .read(&Space::new(export_dir.to_path_buf()), &Options::default())
+            .unwrap();
+        let note = |key: &str| {
+            scan.files
+                .iter()
+                .find(|f| f.path_rel == Path::new(key))
+                .unwrap()
+        };
+
+        let woodworking = note("Woodworking");
+        assert!(woodworking.section);
+        assert_eq!(
+            woodworking.body,
+            "# Woodworking\n\n[[Woodworking/Dovetail Joint|the joint]]\n"
+        );
+
+        let joint = note("Woodworking/Dovetail Joint");
+        assert!(!joint.section);
+        assert_eq!(
+            joint.path_slug,
+            PathBuf::from("woodworking/dovetail-joint/index.md")
+        );
+        assert_eq!(
+            joint.body,
+            "# Dovetail Joint\n\n\
+            ![Untitled.png](Dovetail%20Joint.assets/Untitled.png)\n\
+            [[Projects]]\n"
+        );
+        assert!(joint.find_asset(Path::new("Untitled.png")).is_some());
+
+        let projects = note("Projects");
+        assert!(projects.section);
+        assert_eq!(
+            projects.body,
+            "# Projects\n\n\
+            | Name | Status |\n| --- | --- |\n\
+            | [[Projects/Table Saw]] | Done |\n\
+            | Router | To \\| do |\n"
+        );
+    }
+}
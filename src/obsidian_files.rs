@@ -0,0 +1,389 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs::{self, read_to_string},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use walkdir::WalkDir;
+
+use crate::{
+    craft_files::{add_ext, strip_ext, Asset, FileData, Options, Space, MD_EXT},
+    frontmatter::Frontmatter,
+    reader::{asset_link, is_visible, with_title, Reader, Scan},
+    transforms::{Code, Diagnostics},
+};
+
+lazy_static! {
+    // An internal link or an embed
+    // Names the capture group "bang" for the leading '!' of an embed, "target" for the note or
+    // file, "anchor" for the heading or ^block after the '#' and "text" for everything after the
+    // '|', the display text of a link or the size of an embedded image
+    //
+    // Example:
+    //  [[Dovetail Joint#Marking out|marking out]]
+    //  ![[Image 1.png|300]]
+    static ref RE_OBSIDIAN_LINK: Regex = Regex::new(
+        r"(?<bang>!?)\[\[(?<target>[^\[\]|#]*)(?:#(?<anchor>[^\[\]|]*))?(?:\|(?<text>[^\[\]]*))?\]\]"
+    )
+    .unwrap();
+}
+
+/// The vault's settings, relative to the vault
+const CONFIG_FILE: &str = ".obsidian/app.json";
+
+/// Obsidian, chosen with `--from obsidian`
+///
+/// Links and embeds name a note or a file by the shortest path which is unique in the vault, or a
+/// note by one of its `aliases`, and are resolved to the full path. Files are looked up in the
+/// vault's attachment folder first (the "Default location for new attachments" setting). Embedded
+/// notes become links, embedded files are copied next to the note. The display text of a link is
+/// kept, a link without one shows the note's title.
+///
+/// A note's YAML properties are read the same way as Craft's frontmatter (dates, `draft`,
+/// `private`), `title` replaces the note's file name and `publish: false` leaves it out.
+///
+/// Example:
+///     "See [[Dovetails|the joint]]" -> "See [[Woodworking/Dovetail Joint|the joint]]"
+///     "![[Image 1.png|300]]"        -> "![Image 1.png](Tools.assets/Attachments/Image%201.png)"
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Obsidian;
+
+impl Reader for Obsidian {
+    fn name(&self) -> &str {
+        "obsidian"
+    }
+
+    fn read(&self, space: &Space, options: &Options) -> anyhow::Result<Scan> {
+        let vault = Vault::load(&space.input_dir)?;
+        let slugify_name = options.slugify_assets.then_some(options.slugify_paths);
+        let mut files = Vec::new();
+        let mut diagnostics = Diagnostics::default();
+        for (key, body) in &vault.notes {
+            let full_path = space.input_dir.join(add_md(key));
+            let mut file_data = FileData::try_from(full_path.clone())?;
+            file_data.set_paths(&space.input_dir, &space.prefix, options.slugify_paths)?;
+            let (frontmatter, _) = Frontmatter::split(body).with_context(|| {
+                format!("Invalid frontmatter in file = {}", full_path.display())
+            })?;
+            if let Some(title) = frontmatter.and_then(|f| f.get_str(&["title"])) {
+                file_data.name = title;
+            }
+
+            let mut attachments = BTreeSet::new();
+            let body = vault.rewrite_links(
+                key,
+                &file_data.name,
+                body,
+                &mut attachments,
+                &full_path,
+                &mut diagnostics,
+            );
+            file_data.body = with_title(&body, &file_data.name);
+            if !attachments.is_empty() {
+                // Attachments are copied next to the note, keeping their path inside the vault
+                // Example: "Attachments/Image 1.png" -> "dovetail-joint/Attachments/Image 1.png"
+                file_data.assets = Some(
+                    attachments
+                        .into_iter()
+                        .map(|path_rel| Asset::new(path_rel, slugify_name))
+                        .collect(),
+                );
+                file_data.assets_dir = Some(space.input_dir.clone());
+                let page_slug = strip_ext(&file_data.path_slug, MD_EXT);
+                file_data.path_slug = options.backend.page_path(&page_slug, true);
+            }
+            files.push(file_data);
+        }
+        Ok(Scan {
+            files,
+            directories: vault
+                .directories
+                .iter()
+                .map(|dir| space.prefix.join(dir))
+                .collect(),
+            diagnostics,
+            ..Default::default()
+        })
+    }
+}
+
+/// `.obsidian/app.json`, only the settings we need
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AppConfig {
+    /// Example: "/" (the vault's root), "./" (next to the note), "./attachments", "Attachments"
+    #[serde(default)]
+    attachment_folder_path: String,
+}
+
+impl AppConfig {
+    /// The directory new attachments of a note in `note_dir` go to, relative to the vault
+    fn attachment_dir(&self, note_dir: &Path) -> PathBuf {
+        let folder = self.attachment_folder_path.trim_end_matches('/');
+        match folder.strip_prefix('.') {
+            Some(below_note) => note_dir.join(below_note.trim_start_matches('/')),
+            None => PathBuf::from(folder.trim_start_matches('/')),
+        }
+    }
+}
+
+/// Every note and file of a vault, relative to the vault
+#[derive(Debug, Default)]
+struct Vault {
+    config: AppConfig,
+    /// The notes by their path without `.md`, with their body
+    notes: BTreeMap<PathBuf, String>,
+    /// The notes by their lowercase file name without `.md`
+    notes_by_name: HashMap<String, Vec<PathBuf>>,
+    /// The notes by their lowercase aliases
+    aliases: HashMap<String, PathBuf>,
+    /// The files which are not notes by their lowercase file name
+    files_by_name: HashMap<String, Vec<PathBuf>>,
+    directories: Vec<PathBuf>,
+}
+
+impl Vault {
+    fn load(vault_dir: &Path) -> anyhow::Result<Self> {
+        let mut vault = Vault::default();
+        let config_path = vault_dir.join(CONFIG_FILE);
+        if config_path.is_file() {
+            let json = fs::read_to_string(&config_path)?;
+            vault.config = serde_json::from_str(&json).with_context(|| {
+                format!(
+                    "Invalid Obsidian settings in file = {}",
+                    config_path.display()
+                )
+            })?;
+        }
+
+        // Skips the ".obsidian" settings and the ".trash" as well
+        for entry in WalkDir::new(vault_dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(is_visible)
+        {
+            let entry = entry?;
+            let full_path = entry.path();
+            let rel_path = full_path.strip_prefix(vault_dir)?.to_path_buf();
+            if rel_path.as_os_str().is_empty() {
+                continue;
+            }
+            if full_path.is_dir() {
+                vault.directories.push(rel_path);
+                continue;
+            }
+            let is_note = rel_path.extension().is_some_and(|ext| ext == MD_EXT);
+            if !is_note {
+                vault
+                    .files_by_name
+                    .entry(lowercase_name(&rel_path))
+                    .or_default()
+                    .push(rel_path);
+                continue;
+            }
+
+            let body = read_to_string(full_path)
+                .with_context(|| format!("Failed to read file = {}", full_path.display()))?;
+            let key = strip_ext(&rel_path, MD_EXT);
+            if let (Some(frontmatter), _) = Frontmatter::split(&body)
+                .with_context(|| format!("Invalid frontmatter in file = {}", full_path.display()))?
+            {
+                for alias in frontmatter.get_list("aliases") {
+                    vault.aliases.insert(alias.to_lowercase(), key.clone());
+                }
+            }
+            vault
+                .notes_by_name
+                .entry(lowercase_name(&key))
+                .or_default()
+                .push(key.clone());
+            vault.notes.insert(key, body);
+        }
+        Ok(vault)
+    }
+
+    /// Rewrite the links and embeds of the note `key` into Craft's flavour of Markdown, the
+    /// files it embeds or links to are added to `attachments`
+    ///
+    /// Links to nothing in the vault are reported to `diagnostics` against the note's file at
+    /// `full_path`
+    fn rewrite_links(
+        &self,
+        key: &Path,
+        name: &str,
+        body: &str,
+        attachments: &mut BTreeSet<PathBuf>,
+        full_path: &Path,
+        diagnostics: &mut Diagnostics,
+    ) -> String {
+        let note_dir = key.parent().unwrap_or(Path::new(""));
+        RE_OBSIDIAN_LINK
+            .replace_all(body, |caps: &Captures| {
+                let embed = !caps["bang"].is_empty();
+                let text = caps.name("text").map(|m| m.as_str());
+                let anchor = caps.name("anchor").map(|m| m.as_str().trim());
+                // A link to a heading of the same note
+                // Example: [[#Marking out]]
+                let target = match caps["target"].trim() {
+                    "" => key.to_string_lossy().into_owned(),
+                    target => target.to_string(),
+                };
+
+                if let Some(to) = self.resolve_note(note_dir, &target) {
+                    // Block references only exist inside Obsidian, link to the note instead
+                    let header = match anchor {
+                        Some(anchor) if !anchor.is_empty() && !anchor.starts_with('^') => {
+                            format!("#{anchor}")
+                        }
+                        _ => String::new(),
+                    };
+                    let text = match text {
+                        Some(text) if !text.trim().is_empty() => format!("|{text}"),
+                        _ => String::new(),
+                    };
+                    return format!("[[{}{header}{text}]]", to.display());
+                }
+                if let Some(file) = self.resolve_file(note_dir, &target) {
+                    let file_name = file.file_name().unwrap_or_default().to_string_lossy();
+                    // The text of an embedded image is its size
+                    let text = match (embed, text) {
+                        (false, Some(text)) => text.to_string(),
+                        _ => file_name.into_owned(),
+                    };
+                    let link = asset_link(embed, &text, name, &file);
+                    attachments.insert(file);
+                    return link;
+                }
+
+                let link = caps.get(0).unwrap();
+                diagnostics
+                    .warning(
+                        Code::UnresolvedLink,
+                        full_path,
+                        format!(
+                            "the link = {} points to nothing in the vault, it is written as plain text",
+                            link.as_str()
+                        ),
+                    )
+                    .at_range(body, link.range());
+                text.unwrap_or(&target).to_string()
+            })
+            .into_owned()
+    }
+
+    /// The key of the note `target` names, as seen from a note in `note_dir`
+    ///
+    /// Example: "Dovetail Joint", "Joinery/Dovetail Joint" or an alias "Dovetails"
+    fn resolve_note(&self, note_dir: &Path, target: &str) -> Option<PathBuf> {
+        let target = strip_ext(Path::new(target), MD_EXT);
+        let candidates = self.notes_by_name.get(&lowercase_name(&target));
+        closest(note_dir, candidates, &target)
+            .or_else(|| self.aliases.get(&target.to_string_lossy().to_lowercase()))
+            .cloned()
+    }
+
+    /// The path of the file `target` names, as seen from a note in `note_dir`
+    ///
+    /// Files in the attachment folder win over files with the same name elsewhere
+    fn resolve_file(&self, note_dir: &Path, target: &str) -> Option<PathBuf> {
+        let target = Path::new(target);
+        let attachment_dir = self.config.attachment_dir(note_dir);
+        let candidates = self.files_by_name.get(&lowercase_name(target))?;
+        let in_attachment_dir: Vec<PathBuf> = candidates
+            .iter()
+            .filter(|p| p.parent() == Some(attachment_dir.as_path()))
+            .cloned()
+            .collect();
+        closest(note_dir, Some(&in_attachment_dir), target)
+            .or_else(|| closest(note_dir, Some(candidates), target))
+            .cloned()
+    }
+}
+
+/// The candidate whose path ends with `target` which is in `note_dir` or has the shortest path
+fn closest<'a>(
+    note_dir: &Path,
+    candidates: Option<&'a Vec<PathBuf>>,
+    target: &Path,
+) -> Option<&'a PathBuf> {
+    let target: Vec<String> = lowercase_components(target);
+    candidates?
+        .iter()
+        .filter(|p| lowercase_components(p).ends_with(&target))
+        .min_by_key(|p| (p.parent() != Some(note_dir), p.components().count(), *p))
+}
+
+fn lowercase_components(p: &Path) -> Vec<String> {
+    p.components()
+        .map(|x| x.as_os_str().to_string_lossy().to_lowercase())
+        .collect()
+}
+
+fn lowercase_name(p: &Path) -> String {
+    p.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase()
+}
+
+fn add_md(key: &Path) -> PathBuf {
+    add_ext(key, MD_EXT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::TempDir;
+
+    #[test]
+    fn links_resolve_by_name_alias_and_attachment_folder() {
+        let vault_dir = TempDir::new("vault");
+        vault_dir.write(CONFIG_FILE, r#"{"attachmentFolderPath": "Attachments"}"#);
+        vault_dir.write("Attachments/Image 1.png", "");
+        vault_dir.write("Woodworking/Image 1.png", "");
+        vault_dir.write(
+            "Woodworking/Dovetail Joint.md",
+            "---\naliases: [Dovetails]\n---\n# Dovetail Joint\n",
+        );
+        vault_dir.write(
+            "Notes/Tools.md",
+            "See [[Dovetails|the joint]] and [[dovetail joint#^a1b2c3]].\n\
+            ![[Image 1.png|300]]\n\
+            [[Missing]]\n",
+        );
+
+        let scan = Obsidian
+            .read(&Space::new(vault_dir.to_path_buf()), &Options::default())
+            .unwrap();
+        let tools = scan
+            .files
+            .iter()
+            .find(|f| f.path_rel == Path::new("Notes/Tools"))
+            .unwrap();
+        assert_eq!(
+            tools.body,
+            "# Tools\n\
+            See [[Woodworking/Dovetail Joint|the joint]] and [[Woodworking/Dovetail Joint]].\n\
+            ![Image 1.png](Tools.assets/Attachments/Image%201.png)\n\
+            Missing\n"
+        );
+        assert_eq!(
+            tools
+                .find_asset(Path::new("Attachments/Image 1.png"))
+                .map(|a| &a.path_slug),
+            Some(&PathBuf::from("Attachments/Image 1.png"))
+        );
+        assert_eq!(tools.path_slug, PathBuf::from("notes/tools/index.md"));
+
+        let [missing] = &scan.diagnostics.iter().collect::<Vec<_>>()[..] else {
+            panic!("expected a single diagnostic");
+        };
+        assert_eq!(missing.code, Code::UnresolvedLink);
+        assert_eq!(missing.file, vault_dir.join("Notes/Tools.md"));
+        let span = missing.span.as_ref().unwrap();
+        assert_eq!((span.line, span.column, span.length), (3, 1, 11));
+    }
+}
@@ -0,0 +1,198 @@
+use std::{
+    fmt,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    archive::Archive,
+    craft_files::{Craft, FileData, Options, Space},
+    frontmatter::Frontmatter,
+    logseq_files::Logseq,
+    notion_files::Notion,
+    obsidian_files::Obsidian,
+    textbundle_files::TextBundle,
+    transforms::Diagnostics,
+};
+
+/// Reader turns the directory of a space into notes
+///
+/// Every reader produces the same model: a FileData per note whose `body` is written in Craft's
+/// flavour of Markdown, so the rest of the conversion does not need to know where the notes came
+/// from. That means
+///  * links between notes are `[[wiki links]]` to the note's path relative to the space, without
+///    the `.md` extension, and an optional `#header`
+///  * links to assets point into an `.assets` directory, see `asset_link`
+///  * the body starts with the note's title as an H1, see `with_title`
+///  * the note's `assets` are relative to its `assets_dir`
+pub trait Reader: Send + Sync + fmt::Debug {
+    /// Example: "craft"
+    fn name(&self) -> &str;
+
+    /// Read every note of `space`
+    ///
+    /// The `path_rel` of every note starts with the space's prefix (see FileData::set_paths).
+    /// Include and exclude patterns are applied to the notes afterwards.
+    fn read(&self, space: &Space, options: &Options) -> anyhow::Result<Scan>;
+
+    /// The reader accepts a `.zip` file as the input of a space, otherwise the space has to be a
+    /// directory
+    fn reads_archives(&self) -> bool {
+        false
+    }
+
+    /// The body of every note is its file as it was read. Diagnostics about a body only point
+    /// at the line of the file they are about when it is, a reader which rewrites the notes
+    /// (adding a title or converting links) leaves their span out.
+    fn keeps_source(&self) -> bool {
+        false
+    }
+}
+
+/// The input formats built into the CLI
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[non_exhaustive]
+pub enum InputFormat {
+    /// A space exported from Craft as Markdown
+    #[default]
+    Craft,
+    /// An Obsidian vault
+    Obsidian,
+    /// A Logseq graph, every block of a page becomes a paragraph
+    Logseq,
+    /// A Logseq graph, the blocks of a page are kept as nested lists
+    LogseqOutline,
+    /// An unzipped Notion export as "Markdown & CSV"
+    Notion,
+    /// A directory of TextBundles (`.textbundle`) and TextPacks (`.textpack`)
+    #[value(name = "textbundle")]
+    TextBundle,
+}
+
+impl InputFormat {
+    pub fn reader(self) -> Arc<dyn Reader> {
+        match self {
+            Self::Craft => Arc::new(Craft),
+            Self::Obsidian => Arc::new(Obsidian),
+            Self::Logseq => Arc::new(Logseq { outline: false }),
+            Self::LogseqOutline => Arc::new(Logseq { outline: true }),
+            Self::Notion => Arc::new(Notion),
+            Self::TextBundle => Arc::new(TextBundle),
+        }
+    }
+}
+
+/// What a Reader found in a space
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct Scan {
+    pub files: Vec<FileData>,
+    /// The directories holding notes, relative to the space and starting with its prefix
+    pub directories: Vec<PathBuf>,
+    /// The archives the space was read from, their assets are copied out of them
+    pub(crate) archives: Vec<Arc<Archive>>,
+    /// Problems which do not stop the space from being read, see Diagnostics
+    pub diagnostics: Diagnostics,
+}
+
+/// A Markdown link to an asset in the form Craft exports it, which the `asset-links` transform
+/// points at the asset's final name
+///
+/// `path_rel` is the asset's path relative to the note's `assets_dir`, `note_name` can be any
+/// name as only the part after `.assets/` is looked at.
+///
+/// Example:
+///     "Dovetail Joint", "drawings/Image 1.jpeg"
+///     "![Image 1.jpeg](Dovetail%20Joint.assets/drawings/Image%201.jpeg)"
+pub(crate) fn asset_link(image: bool, text: &str, note_name: &str, path_rel: &Path) -> String {
+    let path = path_rel
+        .components()
+        .map(|x| urlencoding::encode(&x.as_os_str().to_string_lossy()).into_owned())
+        .collect::<Vec<_>>()
+        .join("/");
+    format!(
+        "{bang}[{text}]({note}.assets/{path})",
+        bang = if image { "!" } else { "" },
+        note = urlencoding::encode(note_name),
+    )
+}
+
+/// Skip hidden files and directories such as ".DS_Store", the ".git" directory of an export kept
+/// in version control or Obsidian's ".obsidian" settings, but never the input directory itself
+///
+/// Example: `WalkDir::new(input_dir).into_iter().filter_entry(is_visible)`
+pub(crate) fn is_visible(entry: &walkdir::DirEntry) -> bool {
+    entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.')
+}
+
+/// Resolve the `.` and `..` of a relative path
+///
+/// Example: "Woodworking/../Tools/Saw" -> "Tools/Saw"
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Put the note's title in front of its body as an H1 (after its frontmatter) unless the body
+/// starts with an H1 already
+///
+/// Craft exports the title as the first line of a note, which is replaced by the title of the
+/// generated frontmatter. Without it the first heading of the body would be taken for the title.
+pub(crate) fn with_title(body: &str, title: &str) -> String {
+    // Invalid frontmatter is reported once the note is converted
+    let (_, rest) = Frontmatter::split(body).unwrap_or((None, body));
+    if rest.starts_with("# ") {
+        return body.to_string();
+    }
+    let frontmatter = &body[..body.len() - rest.len()];
+    format!("{frontmatter}# {title}\n{rest}")
+}
+
+/// A fresh directory under the system temp dir for a test, removed when it is dropped so it is
+/// cleaned up when the test panics as well
+#[cfg(test)]
+pub(crate) struct TempDir(PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    /// `name` keeps the directories of tests running in parallel apart
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("craft-zola-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    /// Write a file relative to the directory, creating the directories it is in
+    pub fn write(&self, rel_path: &str, contents: &str) -> PathBuf {
+        let path = self.0.join(rel_path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
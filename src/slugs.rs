@@ -0,0 +1,172 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use slug::slugify;
+
+/// How names are turned into slugs, these mirror the modes of Zola's `[slugify]` config
+///
+/// https://www.getzola.org/documentation/getting-started/configuration/#slugification-strategies
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SlugStrategy {
+    /// Transliterate to ASCII, lowercase and replace everything else with '-'
+    ///
+    /// Example: "Crème Brûlée" -> "creme-brulee"
+    #[default]
+    On,
+    /// Keep Unicode, only remove characters which are not allowed in file names
+    ///
+    /// Example: "日本語: 入門?" -> "日本語 入門"
+    Safe,
+    /// Keep the name as it is
+    Off,
+}
+
+impl SlugStrategy {
+    /// Slugify a single component of a path (a file or directory name)
+    pub fn slugify_path(&self, s: &str) -> String {
+        match self {
+            Self::On => slugify(s),
+            Self::Safe => strip_invalid_path_chars(s.trim()),
+            Self::Off => s.trim().into(),
+        }
+    }
+
+    /// Slugify the text of a heading the same way Zola builds the heading's id
+    ///
+    /// Example: [[Weightlifting/Deadlift Variants#Conventional deadlifts]]
+    ///     On         -> "conventional-deadlifts"
+    ///     Safe | Off -> "Conventional_deadlifts"
+    pub fn slugify_anchor(&self, s: &str) -> String {
+        match self {
+            Self::On => slugify(s),
+            Self::Safe | Self::Off => s.replace(|c: char| c.is_ascii_whitespace(), "_"),
+        }
+    }
+}
+
+/// Zola's "safe" strategy, strips the characters NTFS does not allow in file names as well as
+/// trailing spaces and dots
+fn strip_invalid_path_chars(s: &str) -> String {
+    s.trim_end_matches([' ', '.'])
+        .chars()
+        .filter(|c| !"<>:/|?*#\\\"".contains(*c))
+        .collect()
+}
+
+/// What to do when two pages, sections or assets end up with the same slug
+///
+/// Example:
+///     "🚀 Rockets.md" -> "rockets.md"
+///     "Rockets.md"    -> "rockets.md"
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[non_exhaustive]
+pub enum CollisionStrategy {
+    /// Stop before anything is written
+    #[default]
+    Error,
+    /// Append "-2", "-3", ... to every colliding slug after the first
+    Numeric,
+    /// Append a short hash of the original path to every colliding slug after the first
+    Hash,
+}
+
+/// A slug which more than one entry of the export maps to
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Collision {
+    /// The slug every entry maps to, relative to the output directory
+    pub slug: PathBuf,
+    /// The original paths (relative to the input directory) of the colliding entries. The first
+    /// one keeps the slug.
+    pub sources: Vec<PathBuf>,
+    /// The slug each of the other sources was renamed to (empty with CollisionStrategy::Error)
+    pub renamed: Vec<PathBuf>,
+}
+
+impl Display for Collision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sources = self
+            .sources
+            .iter()
+            .map(|s| format!("'{}'", s.display()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "{sources} all map to the slug '{}'", self.slug.display())?;
+        for (source, renamed) in self.sources.iter().skip(1).zip(&self.renamed) {
+            write!(
+                f,
+                ", renamed '{}' to '{}'",
+                source.display(),
+                renamed.display()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl CollisionStrategy {
+    /// Produce a new slug for `slug` which is not in use yet
+    ///
+    /// `original` is the unslugified path of the entry being renamed, it seeds the hash suffix
+    /// so the same entry is renamed the same way on every run.
+    ///
+    /// Returns None for CollisionStrategy::Error
+    pub fn resolve(
+        &self,
+        slug: &str,
+        original: &Path,
+        is_taken: impl Fn(&str) -> bool,
+    ) -> Option<String> {
+        let candidate = match self {
+            Self::Error => return None,
+            Self::Numeric => (2..)
+                .map(|n| format!("{slug}-{n}"))
+                .find(|c| !is_taken(c))
+                .unwrap(),
+            Self::Hash => format!("{slug}-{}", short_hash(original)),
+        };
+        // A hash suffix could (very unlikely) be taken as well, fall back to numbering it
+        if is_taken(&candidate) {
+            return Self::Numeric.resolve(&candidate, original, is_taken);
+        }
+        Some(candidate)
+    }
+}
+
+/// The first 8 hex characters of the SHA-256 of a path
+fn short_hash(p: &Path) -> String {
+    let digest = Sha256::digest(p.to_string_lossy().as_bytes());
+    digest[..4].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slug_strategies_match_zola() {
+        let name = "Crème Brûlée: v2.0?";
+        assert_eq!(SlugStrategy::On.slugify_path(name), "creme-brulee-v2-0");
+        assert_eq!(SlugStrategy::Safe.slugify_path(name), "Crème Brûlée v2.0");
+        assert_eq!(SlugStrategy::Off.slugify_path(name), name);
+
+        let heading = "Conventional deadlifts";
+        assert_eq!(
+            SlugStrategy::On.slugify_anchor(heading),
+            "conventional-deadlifts"
+        );
+        assert_eq!(
+            SlugStrategy::Safe.slugify_anchor(heading),
+            "Conventional_deadlifts"
+        );
+        assert_eq!(
+            SlugStrategy::Off.slugify_anchor(heading),
+            "Conventional_deadlifts"
+        );
+    }
+}
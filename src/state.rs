@@ -0,0 +1,118 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// State is what we remember about every note between two runs. It is stored in the OUTPUT_DIR
+/// next to the generated files.
+///
+/// When a note is renamed in Craft its slug, and therefore its URL, changes. Remembering the
+/// URLs a note had before lets us add them as Zola `aliases` so old links keep working.
+///
+/// A fresh Craft export also resets the files' timestamps, so the state doubles as a ledger of
+/// when a note first appeared and when its content last changed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct State {
+    #[serde(default, rename = "note")]
+    pub notes: Vec<NoteState>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoteState {
+    /// The note's path relative to the INPUT directory without the `.md` extension (path_rel)
+    pub path: PathBuf,
+    /// See `fingerprint`, used to find the note again after it was renamed
+    pub fingerprint: String,
+    /// The URL the note was published at
+    ///
+    /// Example: "/garden/woodworking/dovetail-joint/"
+    pub url: String,
+    /// Every URL the note was published at before
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// The ledger: when the note was first seen
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    /// The ledger: when the note's content last changed (its fingerprint)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated: Option<String>,
+}
+
+impl State {
+    pub const FILE_NAME: &'static str = ".craft-zola.toml";
+
+    /// Load the state of the previous run, a missing file means this is the first run
+    pub fn load(output_dir: &Path) -> anyhow::Result<Self> {
+        let state_path = output_dir.join(Self::FILE_NAME);
+        if !state_path.try_exists()? {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(&state_path)
+            .with_context(|| format!("Failed to read the state file = {}", state_path.display()))?;
+        toml::from_str(&contents).with_context(|| {
+            format!(
+                "Failed to parse the state file = {}, delete it to start over",
+                state_path.display()
+            )
+        })
+    }
+
+    pub fn save(&self, output_dir: &Path) -> anyhow::Result<()> {
+        let state_path = output_dir.join(Self::FILE_NAME);
+        let contents = toml::to_string(self).context("Failed to serialize the state")?;
+        fs::create_dir_all(output_dir)?;
+        fs::write(&state_path, contents)
+            .with_context(|| format!("Failed to write the state file = {}", state_path.display()))
+    }
+}
+
+/// A fingerprint of a note's body which stays the same when the note is renamed
+///
+/// Craft exports the note's title as the first `# H1`, so renaming a note changes its first line.
+/// The caller is expected to remove it first.
+pub fn fingerprint(body: &str) -> String {
+    let digest = Sha256::digest(body.trim().as_bytes());
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::TempDir;
+
+    #[test]
+    fn state_round_trips_through_the_state_file() {
+        let output_dir = TempDir::new("state");
+        assert!(State::load(&output_dir).unwrap().notes.is_empty());
+
+        let notes = vec![
+            NoteState {
+                path: "Woodworking/Dovetail Joint".into(),
+                fingerprint: fingerprint("Cut the tails first."),
+                url: "/garden/woodworking/dovetail-joint/".into(),
+                aliases: vec![
+                    "/garden/woodworking/dovetails/".into(),
+                    "/garden/say \"hi\"/".into(),
+                ],
+                created: Some("2023-01-14T09:30:00Z".into()),
+                updated: Some("2023-02-01T18:00:00Z".into()),
+            },
+            NoteState {
+                path: "Empty".into(),
+                fingerprint: fingerprint(""),
+                url: "/garden/empty/".into(),
+                ..Default::default()
+            },
+        ];
+        State {
+            notes: notes.clone(),
+        }
+        .save(&output_dir)
+        .unwrap();
+        assert_eq!(State::load(&output_dir).unwrap().notes, notes);
+    }
+}
@@ -0,0 +1,320 @@
+use std::{
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use regex::{Captures, Regex};
+use serde_json::Value;
+use walkdir::WalkDir;
+
+use crate::{
+    archive::Archive,
+    craft_files::{strip_ext, Asset, FileData, Options, Space, MD_EXT},
+    dates,
+    reader::{asset_link, is_visible, with_title, Reader, Scan},
+};
+
+lazy_static! {
+    // A link to a file in the bundle's assets directory, URL-encoded
+    // Names the capture group "bang" for the optional leading '!' of an image, "text" for the link
+    // text and "path" for the file's path inside the assets directory
+    //
+    // Example: ![Image 1.jpeg](assets/Image%201.jpeg)
+    static ref RE_BUNDLE_ASSET_LINK: Regex =
+        Regex::new(r"(?<bang>!?)\[(?<text>[^\[\]]*)\]\(assets/(?<path>[^()\s]+)\)").unwrap();
+}
+
+const BUNDLE_EXT: &str = "textbundle";
+const PACK_EXT: &str = "textpack";
+/// The note of a bundle, the spec allows any extension for plain text or Markdown
+const TEXT_FILES: [&str; 2] = ["text.md", "text.markdown"];
+const INFO_FILE: &str = "info.json";
+const ASSETS_DIR: &str = "assets";
+
+/// TextBundles (`.textbundle` directories) and TextPacks (zipped TextBundles), chosen with
+/// `--from textbundle`
+///
+/// Every bundle in the input directory is a note, named after the bundle. The files in the
+/// bundle's `assets/` directory are the note's assets. `info.json` can give the note a `title`
+/// and its dates, either at the top level or in the object of the app which wrote the bundle.
+///
+/// Example:
+///     "Woodworking/Dovetail Joint.textpack" -> "Woodworking/Dovetail Joint"
+///     "![Plan](assets/plan.png)"            -> "![Plan](Dovetail%20Joint.assets/plan.png)"
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TextBundle;
+
+impl Reader for TextBundle {
+    fn name(&self) -> &str {
+        "textbundle"
+    }
+
+    fn read(&self, space: &Space, options: &Options) -> anyhow::Result<Scan> {
+        let mut scan = Scan::default();
+        let mut entries = WalkDir::new(&space.input_dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(is_visible);
+        while let Some(entry) = entries.next() {
+            let entry = entry?;
+            let full_path = entry.path();
+            let bundle = match full_path.extension().and_then(|ext| ext.to_str()) {
+                Some(BUNDLE_EXT) if entry.file_type().is_dir() => {
+                    // The bundle's files are read through the bundle
+                    entries.skip_current_dir();
+                    Bundle::Dir(full_path.to_path_buf())
+                }
+                Some(PACK_EXT) if entry.file_type().is_file() => {
+                    let archive = Arc::new(Archive::open(full_path, &mut scan.diagnostics)?);
+                    scan.archives.push(archive.clone());
+                    Bundle::Pack(archive)
+                }
+                _ if entry.file_type().is_dir() && entry.depth() > 0 => {
+                    scan.directories
+                        .push(space.prefix.join(full_path.strip_prefix(&space.input_dir)?));
+                    continue;
+                }
+                _ => continue,
+            };
+            let file_data = bundle
+                .note(space, options)
+                .with_context(|| format!("Failed to read the bundle = {}", full_path.display()))?;
+            scan.files.push(file_data);
+        }
+        Ok(scan)
+    }
+}
+
+/// A TextBundle, unpacked or zipped
+enum Bundle {
+    Dir(PathBuf),
+    Pack(Arc<Archive>),
+}
+
+impl Bundle {
+    /// Example: "Exports/Garden/Dovetail Joint.textpack"
+    fn path(&self) -> &Path {
+        match self {
+            Self::Dir(path) => path,
+            Self::Pack(archive) => archive.path(),
+        }
+    }
+
+    fn contains(&self, name: &Path) -> bool {
+        match self {
+            Self::Dir(path) => path.join(name).is_file(),
+            Self::Pack(archive) => archive.contains(name),
+        }
+    }
+
+    fn read_to_string(&self, name: &Path) -> anyhow::Result<String> {
+        match self {
+            Self::Dir(path) => {
+                let path = path.join(name);
+                read_to_string(&path)
+                    .with_context(|| format!("Failed to read file = {}", path.display()))
+            }
+            Self::Pack(archive) => archive.read_to_string(name),
+        }
+    }
+
+    /// The files in the bundle's assets directory, relative to it
+    fn assets(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let assets = match self {
+            Self::Dir(path) => {
+                // The assets directory is optional
+                let assets_dir = path.join(ASSETS_DIR);
+                let mut assets = Vec::new();
+                if !assets_dir.try_exists()? {
+                    return Ok(assets);
+                }
+                for entry in WalkDir::new(&assets_dir).sort_by_file_name() {
+                    let entry = entry?;
+                    let hidden = entry.file_name().to_string_lossy().starts_with('.');
+                    if entry.file_type().is_file() && !hidden {
+                        assets.push(entry.path().strip_prefix(&assets_dir)?.to_path_buf());
+                    }
+                }
+                assets
+            }
+            Self::Pack(archive) => archive
+                .files()
+                .filter_map(|name| name.strip_prefix(ASSETS_DIR).ok())
+                .map(Path::to_path_buf)
+                .collect(),
+        };
+        Ok(assets)
+    }
+
+    fn note(&self, space: &Space, options: &Options) -> anyhow::Result<FileData> {
+        let text_file = TEXT_FILES
+            .iter()
+            .map(Path::new)
+            .find(|name| self.contains(name))
+            .with_context(|| format!("The bundle has none of the files = {TEXT_FILES:?}"))?;
+        let full_path = self.path().join(text_file);
+        let mut file_data = match self {
+            Self::Dir(_) => FileData::try_from(full_path)?,
+            Self::Pack(archive) => {
+                // Zip only records when an entry was modified
+                let modified = archive.modified(text_file)?;
+                FileData::with_dates(full_path, modified, modified)?
+            }
+        };
+
+        // "Woodworking/Dovetail Joint.textpack" -> "Woodworking/Dovetail Joint"
+        let key = self
+            .path()
+            .strip_prefix(&space.input_dir)?
+            .with_extension("");
+        file_data.set_key(&key, &space.prefix, options.slugify_paths);
+        file_data.name = key.file_name().unwrap_or_default().to_string_lossy().into();
+
+        if self.contains(Path::new(INFO_FILE)) {
+            let info: Value = serde_json::from_str(&self.read_to_string(Path::new(INFO_FILE))?)
+                .with_context(|| format!("Invalid {INFO_FILE}"))?;
+            let info = Info(info);
+            if let Some(title) = info.get(&["title"]) {
+                file_data.name = title.into();
+            }
+            if let Some(created) = info.date(&["created", "creationDate", "createdAt"]) {
+                file_data.created_at = created;
+            }
+            if let Some(modified) = info.date(&["modified", "modificationDate", "modifiedAt"]) {
+                file_data.modified_at = modified;
+            }
+        }
+
+        let body = self.read_to_string(text_file)?;
+        let body = RE_BUNDLE_ASSET_LINK.replace_all(&body, |caps: &Captures| {
+            let Ok(path) = urlencoding::decode(&caps["path"]) else {
+                return caps[0].to_string();
+            };
+            asset_link(
+                !caps["bang"].is_empty(),
+                &caps["text"],
+                &file_data.name,
+                Path::new(path.as_ref()),
+            )
+        });
+        file_data.body = with_title(&body, &file_data.name);
+
+        let assets = self.assets()?;
+        if !assets.is_empty() {
+            let slugify_name = options.slugify_assets.then_some(options.slugify_paths);
+            file_data.assets = Some(
+                assets
+                    .into_iter()
+                    .map(|path_rel| Asset::new(path_rel, slugify_name))
+                    .collect(),
+            );
+            file_data.assets_dir = Some(self.path().join(ASSETS_DIR));
+            let page_slug = strip_ext(&file_data.path_slug, MD_EXT);
+            file_data.path_slug = options.backend.page_path(&page_slug, true);
+        }
+        Ok(file_data)
+    }
+}
+
+/// A bundle's `info.json`
+///
+/// Apps put their own keys in an object named after them.
+///
+/// Example: {"version": 2, "com.lukilabs.lukiapp": {"created": "2023-08-14T09:12:00Z"}}
+struct Info(Value);
+
+impl Info {
+    /// The first of `keys` at the top level, or in any app's object
+    fn get(&self, keys: &[&str]) -> Option<&str> {
+        let Value::Object(info) = &self.0 else {
+            return None;
+        };
+        let apps = info.values().filter_map(Value::as_object);
+        std::iter::once(info)
+            .chain(apps)
+            .find_map(|object| keys.iter().find_map(|key| object.get(*key)?.as_str()))
+    }
+
+    fn date(&self, keys: &[&str]) -> Option<String> {
+        dates::parse_date(self.get(keys)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::TempDir;
+    use std::{fs, io::Write};
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    #[test]
+    fn bundles_and_packs_become_notes() {
+        let input_dir = TempDir::new("textbundle");
+        let bundle = input_dir.join("Woodworking/Dovetail Joint.textbundle");
+        input_dir.write(
+            "Woodworking/Dovetail Joint.textbundle/text.md",
+            "# Dovetail Joint\n\n![Plan](assets/Image%201.png)\n",
+        );
+        input_dir.write(
+            "Woodworking/Dovetail Joint.textbundle/assets/Image 1.png",
+            "png",
+        );
+        input_dir.write(
+            "Woodworking/Dovetail Joint.textbundle/info.json",
+            r#"{"version": 2, "com.lukilabs.lukiapp": {"created": "2023-08-14T09:12:00Z"}}"#,
+        );
+
+        let mut pack = ZipWriter::new(fs::File::create(input_dir.join("Saws.textpack")).unwrap());
+        for (name, contents) in [
+            ("Saws.textbundle/text.md", "Rip and crosscut\n"),
+            ("Saws.textbundle/info.json", r#"{"title": "Hand Saws"}"#),
+        ] {
+            pack.start_file(name, SimpleFileOptions::default()).unwrap();
+            pack.write_all(contents.as_bytes()).unwrap();
+        }
+        pack.finish().unwrap();
+
+        let scan = TextBundle
+            .read(&Space::new(input_dir.to_path_buf()), &Options::default())
+            .unwrap();
+        assert_eq!(scan.directories, vec![PathBuf::from("Woodworking")]);
+        assert_eq!(scan.archives.len(), 1);
+        let [saws, joint] = &scan.files[..] else {
+            panic!("expected two notes, found {:?}", scan.files);
+        };
+
+        assert_eq!(saws.path_rel, PathBuf::from("Saws"));
+        assert_eq!(saws.name, "Hand Saws");
+        assert_eq!(saws.body, "# Hand Saws\nRip and crosscut\n");
+
+        assert_eq!(joint.path_rel, PathBuf::from("Woodworking/Dovetail Joint"));
+        assert_eq!(joint.created_at, "2023-08-14T09:12:00Z");
+        assert_eq!(
+            joint.body,
+            "# Dovetail Joint\n\n![Plan](Dovetail%20Joint.assets/Image%201.png)\n"
+        );
+        assert!(joint.find_asset(Path::new("Image 1.png")).is_some());
+        assert_eq!(joint.assets_dir, Some(bundle.join("assets")));
+        assert_eq!(
+            joint.path_slug,
+            PathBuf::from("woodworking/dovetail-joint/index.md")
+        );
+    }
+
+    #[test]
+    fn bundles_without_assets_are_read() {
+        let input_dir = TempDir::new("bundle-no-assets");
+        input_dir.write("Chisels.textbundle/text.md", "Bevel down\n");
+
+        let scan = TextBundle
+            .read(&Space::new(input_dir.to_path_buf()), &Options::default())
+            .unwrap();
+        let [chisels] = &scan.files[..] else {
+            panic!("expected a single note, found {:?}", scan.files);
+        };
+        assert_eq!(chisels.body, "# Chisels\nBevel down\n");
+        assert_eq!(chisels.assets, None);
+    }
+}
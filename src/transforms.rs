@@ -0,0 +1,955 @@
+use std::{
+    fmt,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use regex::{Captures, Match, Regex};
+use serde::Serialize;
+use time::{macros::format_description, Date};
+
+use crate::craft_files::{CraftDocs, FileData};
+
+lazy_static! {
+    // Names the capture group "link_name"
+    //
+    // I noticed that because I am using regular expressions on the note bodies, I accidentially
+    // matched on some example code inside of a code-fence
+    //
+    // Example:
+    // ```purescript
+    // list = [[1,2,3], [4,5], [6]]
+    // ```
+    //
+    // I realized that the wiki links regular expression should say
+    // "match on all character inside two brackets, such that every character is itself not a bracket"
+    //
+    // However that does not exclude this code snippet
+    // Example:
+    // ```rust
+    // [[ 1, 2, 3, 4, 5]]
+    //
+    // Basically if I come back to this program because it panicked for some reason, this may be a
+    // reason why... yippie for Regexs!
+    //```
+    //
+    // A link may have its own text after a '|', named "text"
+    // Example: [[Woodworking/Dovetail Joint|the joint]]
+    static ref RE_WIKI_LINK: Regex =
+        Regex::new(r"\[\[(?<link_name>[^\[\]|]+?)(?:\|(?<text>[^\[\]]+?))?\]\]").unwrap();
+    static ref RE_UUID_V4: Regex = Regex::new(
+        r"\#\^[0-9A-Za-z]{8}-[0-9A-Za-z]{4}-4[0-9A-Za-z]{3}-[89ABab][0-9A-Za-z]{3}-[0-9A-Za-z]{12}"
+    )
+    .unwrap();
+    // Names the capture group "header" and "link_name"
+    static ref RE_HEADER_ANCHOR: Regex = Regex::new(r"(?<link_name>.+)(\#(?<header>.+))").unwrap();
+    // Names the capture group "desc" for the date string,
+    // "day_url" for everything including day://,
+    // and "date" for the actual yyyy.mm.dd
+    static ref RE_DAY_LINK: Regex =
+        Regex::new(r"\[(?<desc>.*)\]\((?<day_url>day:\/\/(?<date>\d{4}\.\d{2}\.\d{2}))\)").unwrap();
+    // Names the capture group "bang" for the optional leading '!' of an image link,
+    // "name" for the link text, "owner" for the path of the note the '.assets' directory belongs
+    // to and "file_name" for the path inside the '.assets' directory.
+    //
+    // Matches both images and plain links (PDFs, audio, video, zip, ...)
+    // Example:
+    //  ![Image.jpeg](Non%20Qualified%20Stock%20Options(NSO).assets/Image.jpeg)
+    //  [plan.pdf](Dovetail%20Joint.assets/plan.pdf)
+    //
+    // Craft does not encode parentheses in the asset directory name so a single level of
+    // balanced parentheses is allowed in the path.
+    pub(crate) static ref RE_ASSET_LINK: Regex = Regex::new(
+        r"(?<bang>!?)\[(?<name>[^\[\]]*)\]\((?<owner>(?:[^()\s]|\([^()\s]*\))*?)\.assets/(?<file_name>(?:[^()\s]|\([^()\s]*\))+)\)"
+    )
+    .unwrap();
+    static ref RE_CODE_BLOCK_OTHER: Regex =  Regex::new(r"```other").unwrap();
+}
+
+/// Transform rewrites the body of a note on its way from Craft to Zola
+///
+/// A transform gets the body as left by the transforms before it, without the frontmatter and
+/// without the note's title. Problems which should not stop the conversion go into the
+/// Diagnostics, an `Err` stops it.
+///
+/// Example, a transform which turns Craft's highlights into `<mark>`:
+/// ```
+/// use craft_zola::{CraftDocs, Diagnostics, FileData, Options, Transform};
+///
+/// struct Highlights;
+///
+/// impl Transform for Highlights {
+///     fn name(&self) -> &str {
+///         "highlights"
+///     }
+///
+///     fn transform(
+///         &self,
+///         _note: &FileData,
+///         body: String,
+///         _docs: &CraftDocs,
+///         _diagnostics: &mut Diagnostics,
+///     ) -> anyhow::Result<String> {
+///         Ok(body.replace("==", "<mark>"))
+///     }
+/// }
+///
+/// let mut options = Options::default();
+/// options.transforms.register(Highlights);
+/// ```
+pub trait Transform: Send + Sync {
+    /// Names the transform when it is reordered or disabled
+    ///
+    /// Example: "wiki-links"
+    fn name(&self) -> &str;
+
+    fn transform(
+        &self,
+        note: &FileData,
+        body: String,
+        docs: &CraftDocs,
+        diagnostics: &mut Diagnostics,
+    ) -> anyhow::Result<String>;
+}
+
+/// Pipeline is the ordered list of transforms every note's body goes through
+///
+/// The default pipeline is made of the built-in transforms, in this order:
+///     "wiki-links"  [[Woodworking/Dovetail Joint]] -> [Dovetail Joint](@/garden/woodworking/dovetail-joint.md)
+///     "day-links"   [Jan 4](day://2023.01.04)       -> [Wed, Jan 4 '23](javascript:;)
+///     "asset-links" ![Image](Note.assets/Image.png) -> ![Image](Image.png)
+///     "code-fences" ```other                        -> ```
+#[derive(Clone)]
+pub struct Pipeline {
+    transforms: Vec<Arc<dyn Transform>>,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        let mut pipeline = Self::empty();
+        pipeline.register(WikiLinks);
+        pipeline.register(DayLinks);
+        pipeline.register(AssetLinks);
+        pipeline.register(CodeFences);
+        pipeline
+    }
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+impl Pipeline {
+    /// A pipeline which leaves the body as it is
+    pub fn empty() -> Self {
+        Self {
+            transforms: Vec::new(),
+        }
+    }
+
+    /// Add a transform at the end of the pipeline, or in place of the transform with the same name
+    pub fn register(&mut self, transform: impl Transform + 'static) {
+        let transform: Arc<dyn Transform> = Arc::new(transform);
+        match self.position(transform.name()) {
+            Some(i) => self.transforms[i] = transform,
+            None => self.transforms.push(transform),
+        }
+    }
+
+    /// Remove the transform named `name`
+    pub fn disable(&mut self, name: &str) -> anyhow::Result<()> {
+        let i = self.position(name).with_context(|| self.unknown(name))?;
+        self.transforms.remove(i);
+        Ok(())
+    }
+
+    /// Keep only the transforms in `names`, in that order
+    pub fn set_order(&mut self, names: &[impl AsRef<str>]) -> anyhow::Result<()> {
+        let mut transforms = Vec::with_capacity(names.len());
+        for name in names.iter().map(AsRef::as_ref) {
+            let i = self.position(name).with_context(|| self.unknown(name))?;
+            transforms.push(self.transforms[i].clone());
+        }
+        self.transforms = transforms;
+        Ok(())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.transforms.iter().map(|t| t.name())
+    }
+
+    /// Run every transform in order over the body of `note`
+    pub fn run(
+        &self,
+        note: &FileData,
+        mut body: String,
+        docs: &CraftDocs,
+        diagnostics: &mut Diagnostics,
+    ) -> anyhow::Result<String> {
+        for transform in &self.transforms {
+            body = transform
+                .transform(note, body, docs, diagnostics)
+                .with_context(|| {
+                    format!(
+                        "The transform = {} failed on file = {}",
+                        transform.name(),
+                        note.path_full.display()
+                    )
+                })?;
+        }
+        Ok(body)
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.transforms.iter().position(|t| t.name() == name)
+    }
+
+    fn unknown(&self, name: &str) -> String {
+        format!(
+            "No transform named = {name}, the pipeline has = {}",
+            self.names().collect::<Vec<_>>().join(", ")
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    /// The conversion stops after every note has been transformed
+    Error,
+}
+
+/// What kind of problem a Diagnostic is about, stable so problems can be filtered by it
+///
+/// Example: "broken-link"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Code {
+    /// A [[wiki link]] to a note which is not in the export
+    BrokenLink,
+    /// A link into a note's '.assets' directory to a file which is not in it
+    MissingAsset,
+    /// A file in a note's '.assets' directory which the note never links to
+    UnreferencedAsset,
+    /// A `craftdocs://` link to a block, which only opens inside Craft
+    CraftBlockLink,
+    /// An '.assets' directory without a note of the same name
+    OrphanedAssets,
+    /// A file which is neither a note nor an asset
+    SkippedFile,
+    /// Two pages, sections or assets with the same slug, see CollisionStrategy
+    SlugCollision,
+    /// A link of an Obsidian vault or a Notion export which points to nothing in it
+    UnresolvedLink,
+    /// A Logseq ((block reference)) to a block which is not in the graph
+    BrokenBlockRef,
+    /// An entry of an archive whose path leads outside of it, it is never read
+    UnsafeArchiveEntry,
+    /// git could not be run to look up the dates of the notes, see DateSource::Git
+    GitUnavailable,
+    /// OUTPUT_DIR is not inside the site's content directory and no section was given, see
+    /// ZolaFiles::mount
+    UnmountedOutput,
+    /// A problem found by a Transform from outside this crate
+    Custom(&'static str),
+}
+
+impl Code {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::BrokenLink => "broken-link",
+            Self::MissingAsset => "missing-asset",
+            Self::UnreferencedAsset => "unreferenced-asset",
+            Self::CraftBlockLink => "craft-block-link",
+            Self::OrphanedAssets => "orphaned-assets",
+            Self::SkippedFile => "skipped-file",
+            Self::SlugCollision => "slug-collision",
+            Self::UnresolvedLink => "unresolved-link",
+            Self::BrokenBlockRef => "broken-block-ref",
+            Self::UnsafeArchiveEntry => "unsafe-archive-entry",
+            Self::GitUnavailable => "git-unavailable",
+            Self::UnmountedOutput => "unmounted-output",
+            Self::Custom(code) => code,
+        }
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Code {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// Where in a note a Diagnostic points to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Span {
+    /// Starts at 1
+    pub line: usize,
+    /// Starts at 1, counted in characters
+    pub column: usize,
+    /// Counted in characters
+    pub length: usize,
+    /// The whole line, shown under the message
+    #[serde(skip)]
+    pub source_line: String,
+}
+
+impl Span {
+    /// The bytes `range` of `source`
+    pub fn new(source: &str, range: Range<usize>) -> Self {
+        let start = range.start;
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |i| start + i);
+        Self {
+            line: source[..start].matches('\n').count() + 1,
+            column: source[line_start..start].chars().count() + 1,
+            length: source[range].chars().count(),
+            source_line: source[line_start..line_end].trim_end_matches('\r').into(),
+        }
+    }
+
+    /// The first occurrence of `text` in `source`
+    pub fn find(source: &str, text: &str) -> Option<Self> {
+        let start = source.find(text)?;
+        Some(Self::new(source, start..start + text.len()))
+    }
+}
+
+/// A problem found in a note
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct Diagnostic {
+    pub code: Code,
+    pub severity: Severity,
+    /// The note's file in the export
+    pub file: PathBuf,
+    /// Where in the note's body as it was read, when the problem is about a part of it
+    pub span: Option<Span>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Point the diagnostic at the first occurrence of `text` in `source`, usually the note's
+    /// `body`
+    pub fn at(&mut self, source: &str, text: &str) -> &mut Self {
+        self.span = Span::find(source, text);
+        self
+    }
+
+    /// Point the diagnostic at the bytes `range` of `source`, usually where a regex matched
+    pub fn at_range(&mut self, source: &str, range: Range<usize>) -> &mut Self {
+        self.span = Some(Span::new(source, range));
+        self
+    }
+}
+
+/// Rendered like a compiler error, with the line of the note it points to
+///
+/// Example:
+/// ```text
+/// error[broken-link]: the link = [[Joinery/Mortise]] points to a note which is not in the export
+///   --> Exports/Garden/Woodworking/Dovetail Joint.md:12:5
+///    |
+/// 12 | See [[Joinery/Mortise]] for the other joint
+///    |     ^^^^^^^^^^^^^^^^^^^
+/// ```
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        writeln!(f, "{severity}[{}]: {}", self.code, self.message)?;
+        let Some(span) = &self.span else {
+            return write!(f, "  --> {}", self.file.display());
+        };
+        let gutter = " ".repeat(span.line.to_string().len());
+        writeln!(
+            f,
+            "{gutter}--> {}:{}:{}",
+            self.file.display(),
+            span.line,
+            span.column
+        )?;
+        writeln!(f, "{gutter} |")?;
+        writeln!(f, "{} | {}", span.line, span.source_line)?;
+        write!(
+            f,
+            "{gutter} | {}{}",
+            " ".repeat(span.column - 1),
+            "^".repeat(span.length.max(1))
+        )
+    }
+}
+
+/// How diagnostics are printed, chosen with `--format`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[non_exhaustive]
+pub enum DiagnosticFormat {
+    /// Like a compiler's errors with the lines they point to, on stderr
+    #[default]
+    Human,
+    /// One JSON object per diagnostic and line on stdout, for CI annotations
+    ///
+    /// Example: {"code":"broken-link","severity":"error","file":"Garden/Tools.md","span":{"line":3,"column":5,"length":9},"message":"..."}
+    Json,
+}
+
+/// Diagnostics collects the problems found in the notes, so all of them are reported at once
+/// instead of one per run
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn warning(
+        &mut self,
+        code: Code,
+        file: &Path,
+        message: impl Into<String>,
+    ) -> &mut Diagnostic {
+        self.push(Severity::Warning, code, file, message.into())
+    }
+
+    pub fn error(
+        &mut self,
+        code: Code,
+        file: &Path,
+        message: impl Into<String>,
+    ) -> &mut Diagnostic {
+        self.push(Severity::Error, code, file, message.into())
+    }
+
+    /// Move the diagnostics of `other` after these
+    pub fn append(&mut self, mut other: Diagnostics) {
+        self.diagnostics.append(&mut other.diagnostics);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count()
+    }
+
+    /// Print every diagnostic in the given format
+    pub fn report(&self, format: DiagnosticFormat) -> anyhow::Result<()> {
+        for diagnostic in self.iter() {
+            match format {
+                DiagnosticFormat::Human => eprintln!("{diagnostic}"),
+                DiagnosticFormat::Json => println!("{}", serde_json::to_string(diagnostic)?),
+            }
+        }
+        Ok(())
+    }
+
+    fn push(
+        &mut self,
+        severity: Severity,
+        code: Code,
+        file: &Path,
+        message: String,
+    ) -> &mut Diagnostic {
+        self.diagnostics.push(Diagnostic {
+            code,
+            severity,
+            file: file.to_path_buf(),
+            span: None,
+            message,
+        });
+        self.diagnostics.last_mut().unwrap()
+    }
+}
+
+// The reference for this replacement routine comes from the Regex documentation.
+//
+// When writing a replacement routine where any replacement may fail, you will need to write
+// your own routine on top of replace_all to handle each Result.
+//
+// https://docs.rs/regex/latest/regex/struct.Regex.html#method.replace_all
+fn replace_all<E>(
+    re: &Regex,
+    haystack: &str,
+    mut replacement: impl FnMut(&Captures, &Match) -> Result<String, E>,
+) -> Result<String, E> {
+    let mut new = String::with_capacity(haystack.len());
+    let mut last_match = 0;
+    for caps in re.captures_iter(haystack) {
+        let m = caps.get(0).unwrap();
+        let start_original = m.start();
+        let end_original = m.end();
+        let before = &haystack[last_match..start_original];
+
+        let rep = &replacement(&caps, &m)?;
+
+        new.push_str(before);
+        new.push_str(rep);
+        last_match = end_original;
+    }
+    let after = &haystack[last_match..];
+    new.push_str(after);
+    Ok(new)
+}
+
+/// The byte ranges of the code in a markdown body, fenced code blocks with their fences and
+/// inline code spans with their backticks. Tags and markup inside them are only text.
+///
+/// A fence which is never closed runs to the end of the body, like in CommonMark.
+///
+/// Example:
+/// "Run `cargo test` first" -> [4..16]
+pub(crate) fn code_ranges(body: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    // (marker, length of the opening fence, where the fence starts)
+    let mut fence: Option<(char, usize, usize)> = None;
+    let mut text_start = 0;
+    let mut offset = 0;
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~');
+        let run = marker.map_or(0, |m| trimmed.chars().take_while(|c| *c == m).count());
+        match fence {
+            None if run >= 3 => {
+                inline_code_ranges(&body[text_start..offset], text_start, &mut ranges);
+                fence = Some((marker.unwrap(), run, offset));
+            }
+            Some((m, length, start))
+                if marker == Some(m) && run >= length && trimmed[run..].trim().is_empty() =>
+            {
+                ranges.push(start..offset + line.len());
+                fence = None;
+                text_start = offset + line.len();
+            }
+            _ => {}
+        }
+        offset += line.len();
+    }
+    match fence {
+        Some((_, _, start)) => ranges.push(start..body.len()),
+        None => inline_code_ranges(&body[text_start..], text_start, &mut ranges),
+    }
+    ranges
+}
+
+/// The inline code spans of some text outside of a fence, a span is closed by a run of as many
+/// backticks as it was opened with
+fn inline_code_ranges(text: &str, base: usize, ranges: &mut Vec<Range<usize>>) {
+    let bytes = text.as_bytes();
+    let run_at = |i: usize| bytes[i..].iter().take_while(|b| **b == b'`').count();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'`' {
+            i += 1;
+            continue;
+        }
+        let run = run_at(i);
+        let mut j = i + run;
+        let mut end = None;
+        while j < bytes.len() {
+            if bytes[j] != b'`' {
+                j += 1;
+                continue;
+            }
+            let closing = run_at(j);
+            if closing == run {
+                end = Some(j + closing);
+                break;
+            }
+            j += closing;
+        }
+        match end {
+            Some(end) => {
+                ranges.push(base + i..base + end);
+                i = end;
+            }
+            // Unmatched backticks are literal
+            None => i += run,
+        }
+    }
+}
+
+/// Find all the [[Wiki Links]] and replace them with their Zola internal link equivalent
+pub struct WikiLinks;
+
+impl Transform for WikiLinks {
+    fn name(&self) -> &str {
+        "wiki-links"
+    }
+
+    fn transform(
+        &self,
+        note: &FileData,
+        body: String,
+        docs: &CraftDocs,
+        diagnostics: &mut Diagnostics,
+    ) -> anyhow::Result<String> {
+        replace_all(&RE_WIKI_LINK, &body, |caps, m| {
+            replace_wiki_link(docs, note, caps, m, diagnostics)
+        })
+        .context("Got some invalid [[wiki link]]")
+    }
+}
+
+fn replace_wiki_link(
+    docs: &CraftDocs,
+    file_data: &FileData,
+    captures: &Captures,
+    origin_match: &Match,
+    diagnostics: &mut Diagnostics,
+) -> anyhow::Result<String> {
+    let link_name = captures.name("link_name").context(
+        "Matched on a [[wiki link]] but did not get any value inside the brackets [[ ]]",
+    )?;
+    let text = captures.name("text").map(|m| m.as_str());
+
+    // Does this [[wiki link]] have a Craft Block-ID? (formatted as UUIDv4)
+    // Example: [[Expatriation/Dutch-American Friendship Treaty#^2206D341-3D6E-4F31-B7CF-DD7E3D5D7778]]
+    // Remove it (if no match it returns the original str)
+    let replaced = RE_UUID_V4.replace(link_name.as_str(), "");
+    let mut link_name: &str = replaced.as_ref();
+
+    // Does this [[wiki link]] have a header anchor?
+    // Example: [[Weightlifting/Lower Body Exercises/Deadlift Variants#Conventional deadlifts]]
+    //
+    // Split the header anchor out, slugify it, then add it back into the final zola_link
+    // We are left with our file_name which
+    // should match into the Files HashMap
+    let mut header: Option<String> = None;
+    if let Some(h_cap) = RE_HEADER_ANCHOR.captures(link_name) {
+        let m = h_cap.name("header").unwrap();
+        let header_str = format!(
+            "#{}",
+            docs.options().slugify_anchors.slugify_anchor(m.as_str())
+        );
+        header = header_str.into();
+        let m = h_cap.name("link_name").unwrap();
+        link_name = m.as_str();
+    }
+
+    let key = docs.resolve_link_key(file_data, link_name);
+
+    // A link to a note which is left out of the site becomes plain text
+    // Example: [[Journal/🔒 Salary Negotiation]] -> "🔒 Salary Negotiation"
+    if docs.excluded.contains(&key) {
+        let name = key.file_name().unwrap_or_default().to_string_lossy();
+        return Ok(text.map_or(name.into_owned(), String::from));
+    }
+
+    // The link is left as it was exported, every broken link is reported once all notes are
+    // converted
+    let Some(zola_link) = docs.make_link(file_data, &key, text, header) else {
+        diagnostics
+            .error(
+                Code::BrokenLink,
+                &file_data.path_full,
+                format!(
+                    "the link = {} points to a note which is not in the export, it may be a link to a block inside Craft",
+                    origin_match.as_str()
+                ),
+            )
+            .span = docs.source_span(file_data, origin_match);
+        return Ok(origin_match.as_str().into());
+    };
+    Ok(zola_link)
+}
+
+/// Find all the date links and [Tues, Jan 4](day://2023.01.04) and replace link portion
+pub struct DayLinks;
+
+impl Transform for DayLinks {
+    fn name(&self) -> &str {
+        "day-links"
+    }
+
+    fn transform(
+        &self,
+        _note: &FileData,
+        body: String,
+        _docs: &CraftDocs,
+        _diagnostics: &mut Diagnostics,
+    ) -> anyhow::Result<String> {
+        replace_all(&RE_DAY_LINK, &body, replace_day_link)
+            .context("Got some invalid [day://yyyy.mm.dd]")
+    }
+}
+
+fn replace_day_link(captures: &Captures, origin_match: &Match) -> anyhow::Result<String> {
+    let date = captures.name("date").context(
+        "Matched on a ()[day://yyyy.mm.dd] link but did not get any value for yyyy.mm.dd",
+    )?;
+
+    // Parse the yyyy.mm.dd using the time crate into a Date
+    // Accepted syntax for this macro can be found in the time.rs book
+    // https://time-rs.github.io/book/api/format-description.html
+    let origin_format = format_description!("[year].[month].[day]");
+    // Then reformat that date object into a string to include the year
+    //  "Mon, Jan 3 2023
+    let new_format = format_description!(
+        "[weekday repr:short], [month repr:short] [day padding:none] '[year padding:none repr:last_two]"
+    );
+    let date_obj = Date::parse(date.as_str(), origin_format).with_context(|| {
+        format!(
+            "Unable to parse the day:// URL in our link. match = {} url = {} format = [year].[month].[day]",
+            origin_match.as_str(),
+            date.as_str()
+        )
+    })?;
+    let new_date = date_obj.format(&new_format).with_context(|| {
+        format!(
+            "Unable to format the original date as the new date for match = {} url = {}",
+            origin_match.as_str(),
+            date.as_str()
+        )
+    })?;
+
+    // Since date notes are private and are note exported from Craft, remove the URL from the
+    // link
+    //  [Monday, Jan 3 2023](.)
+    let new_date = format!("[{new_date}](javascript:;)");
+
+    Ok(new_date)
+}
+
+/// Find all image and plain links to media inside '.assets' directories and point them at the
+/// asset copied next to the note
+///
+/// A link into the '.assets' directory of another note points at the asset copied next to that
+/// note, see CraftDocs::linked_asset.
+///
+/// Example:
+///  ![Image.jpeg](Non%20Qualified%20Stock%20Options(NSO).assets/Image.jpeg)
+///                                      only want this part ----^--------^
+///  ![Image.jpeg](Image.jpeg)
+///  [plan.pdf](Dovetail%20Joint.assets/plan.pdf)
+///  [plan.pdf](plan.pdf)
+///  ![teeth.png](../Tools/Saw.assets/teeth.png)
+///  ![teeth.png](/garden/tools/saw/teeth.png)
+pub struct AssetLinks;
+
+impl Transform for AssetLinks {
+    fn name(&self) -> &str {
+        "asset-links"
+    }
+
+    fn transform(
+        &self,
+        note: &FileData,
+        body: String,
+        docs: &CraftDocs,
+        _diagnostics: &mut Diagnostics,
+    ) -> anyhow::Result<String> {
+        replace_all(&RE_ASSET_LINK, &body, |caps, m| {
+            replace_asset_link(docs, note, caps, m)
+        })
+        .context("Tried to parse an asset link but it was invalid")
+    }
+}
+
+fn replace_asset_link(
+    docs: &CraftDocs,
+    file_data: &FileData,
+    captures: &Captures,
+    origin_match: &Match,
+) -> anyhow::Result<String> {
+    // Empty for plain links, "!" for image links
+    let bang = captures.name("bang").map_or("", |m| m.as_str());
+    let name = captures
+        .name("name")
+        .with_context(|| {
+            format!(
+                "Failed to get the asset link's name from within the brackets [] on text = {}",
+                origin_match.as_str()
+            )
+        })?
+        .as_str();
+    let file_name = captures
+        .name("file_name")
+        .with_context(|| {
+            format!(
+                "Failed to get the asset link's file name from within the parentheses () on text = {}",
+                origin_match.as_str()
+            )
+        })?
+        .as_str();
+
+    // Craft writes the links URL-encoded but the files on disk are not
+    // Example: "Image%201.jpeg" -> "Image 1.jpeg"
+    let decoded = urlencoding::decode(file_name).with_context(|| {
+        format!(
+            "Failed to percent-decode the asset link's file name = {file_name} on text = {}",
+            origin_match.as_str()
+        )
+    })?;
+
+    // Point the link at the name the asset will be copied to. If there is no matching asset
+    // leave the link as it was exported.
+    let owner = captures.name("owner").map_or("", |m| m.as_str());
+    let file_name = match docs.linked_asset(file_data, owner, Path::new(decoded.as_ref())) {
+        Some((owner, asset)) if owner.path_rel == file_data.path_rel => asset.link(),
+        Some((owner, asset)) => docs.asset_url(owner, asset),
+        None => file_name.into(),
+    };
+
+    let link = format!("{bang}[{name}]({file_name})");
+    Ok(link)
+}
+
+/// Craft exports code blocks without a language as ```other, which no syntax highlighter knows
+pub struct CodeFences;
+
+impl Transform for CodeFences {
+    fn name(&self) -> &str {
+        "code-fences"
+    }
+
+    fn transform(
+        &self,
+        _note: &FileData,
+        body: String,
+        _docs: &CraftDocs,
+        _diagnostics: &mut Diagnostics,
+    ) -> anyhow::Result<String> {
+        Ok(RE_CODE_BLOCK_OTHER.replace_all(&body, "```").into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::craft_files::Asset;
+
+    #[test]
+    fn asset_links_are_decoded_and_point_at_the_copied_asset() {
+        let slugify = Some(crate::SlugStrategy::default());
+        let note = FileData {
+            assets: Some(vec![
+                Asset::new(PathBuf::from("Image 1.jpeg"), slugify),
+                Asset::new(PathBuf::from("Plans/Café (v2).pdf"), slugify),
+            ]),
+            ..Default::default()
+        };
+        let body = "![Image 1.jpeg](Stock%20Options(NSO).assets/Image%201.jpeg)\n\
+            [Plan](Stock%20Options(NSO).assets/Plans/Caf%C3%A9%20(v2).pdf)\n\
+            [Gone](Stock%20Options(NSO).assets/Gone%201.png)\n";
+        let docs = CraftDocs::new(Vec::new(), String::new(), Default::default());
+        let rewritten = replace_all(&RE_ASSET_LINK, body, |caps, m| {
+            replace_asset_link(&docs, &note, caps, m)
+        })
+        .unwrap();
+        assert_eq!(
+            rewritten,
+            "![Image 1.jpeg](image-1.jpeg)\n\
+            [Plan](plans/cafe-v2.pdf)\n\
+            [Gone](Gone%201.png)\n"
+        );
+
+        let invalid = "[Bad](Note.assets/%FF.png)";
+        assert!(replace_all(&RE_ASSET_LINK, invalid, |caps, m| {
+            replace_asset_link(&docs, &note, caps, m)
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn diagnostics_point_at_the_source() {
+        let body = "# Tools\n\nSee [[Chisel]] and [[Saw]]\n";
+        let mut diagnostics = Diagnostics::default();
+        diagnostics
+            .error(
+                Code::BrokenLink,
+                Path::new("Garden/Tools.md"),
+                "no note is named = Saw",
+            )
+            .at(body, "[[Saw]]");
+        diagnostics.warning(
+            Code::UnreferencedAsset,
+            Path::new("Garden/Tools.md"),
+            "the asset = plan.png is never referenced",
+        );
+        assert_eq!(diagnostics.error_count(), 1);
+
+        let [error, warning] = &diagnostics.iter().collect::<Vec<_>>()[..] else {
+            panic!("expected two diagnostics");
+        };
+        assert_eq!(
+            error.to_string(),
+            "error[broken-link]: no note is named = Saw\n \
+             --> Garden/Tools.md:3:20\n  \
+             |\n\
+             3 | See [[Chisel]] and [[Saw]]\n  \
+             |                    ^^^^^^^"
+        );
+        assert_eq!(
+            warning.to_string(),
+            "warning[unreferenced-asset]: the asset = plan.png is never referenced\n  \
+             --> Garden/Tools.md"
+        );
+        assert_eq!(
+            serde_json::to_string(*error).unwrap(),
+            r#"{"code":"broken-link","severity":"error","file":"Garden/Tools.md","span":{"line":3,"column":20,"length":7},"message":"no note is named = Saw"}"#
+        );
+    }
+
+    #[test]
+    fn code_ranges_cover_fences_and_inline_code() {
+        let body = "Run `cargo test` or ``a ` b`` first\n\
+            ~~~~rust\n\
+            let x = 1;\n\
+            ~~~\n\
+            ~~~~\n\
+            An unmatched ` backtick\n\
+            ```\n\
+            #private\n";
+        let code: Vec<&str> = code_ranges(body).into_iter().map(|r| &body[r]).collect();
+        assert_eq!(
+            code,
+            [
+                "`cargo test`",
+                "``a ` b``",
+                "~~~~rust\nlet x = 1;\n~~~\n~~~~\n",
+                "```\n#private\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn pipeline_register_disable_and_reorder() {
+        let mut pipeline = Pipeline::default();
+        assert_eq!(
+            pipeline.names().collect::<Vec<_>>(),
+            ["wiki-links", "day-links", "asset-links", "code-fences"]
+        );
+
+        pipeline.disable("day-links").unwrap();
+        pipeline.register(DayLinks);
+        assert_eq!(
+            pipeline.names().collect::<Vec<_>>(),
+            ["wiki-links", "asset-links", "code-fences", "day-links"]
+        );
+
+        pipeline.set_order(&["code-fences", "wiki-links"]).unwrap();
+        assert_eq!(
+            pipeline.names().collect::<Vec<_>>(),
+            ["code-fences", "wiki-links"]
+        );
+        assert!(pipeline.disable("day-links").is_err());
+        assert!(pipeline.set_order(&["images"]).is_err());
+    }
+}
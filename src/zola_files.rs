@@ -1,10 +1,107 @@
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
-use anyhow::Context;
+use anyhow::{bail, Context};
+use serde::Deserialize;
 
-use crate::craft_files::CraftDocs;
+use crate::{
+    backend::{link_destination, yaml_aliases, Backend, Written},
+    craft_files::{CraftDocs, FileData},
+    slugs::SlugStrategy,
+};
 
-const DIR_EMOJI: char = '🌳';
+/// The parts of a Zola site's `config.toml` which change how files have to be written
+#[derive(Debug, Default, Deserialize)]
+pub struct ZolaConfig {
+    #[serde(default)]
+    pub slugify: SlugifyConfig,
+    #[serde(default)]
+    pub extra: ExtraConfig,
+}
+
+/// The `[extra]` table, Zola leaves it to the site so our own settings live in it
+#[derive(Debug, Default, Deserialize)]
+pub struct ExtraConfig {
+    #[serde(default)]
+    pub craft_zola: CraftZolaConfig,
+}
+
+/// The `[extra.craft_zola]` table
+///
+/// Example:
+/// ```toml
+/// [extra.craft_zola]
+/// transforms = ["wiki-links", "asset-links"]
+/// disable_transforms = ["asset-links"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct CraftZolaConfig {
+    /// The transforms to run and their order, see Pipeline
+    pub transforms: Option<Vec<String>>,
+    /// Transforms to leave out of the pipeline
+    #[serde(default)]
+    pub disable_transforms: Vec<String>,
+}
+
+/// The `[slugify]` table, a missing key means Zola's default ("on")
+#[derive(Debug, Default, Deserialize)]
+pub struct SlugifyConfig {
+    pub paths: Option<SlugStrategy>,
+    pub anchors: Option<SlugStrategy>,
+}
+
+impl ZolaConfig {
+    pub fn read(config_path: &Path) -> anyhow::Result<Self> {
+        let contents = fs::read_to_string(config_path).with_context(|| {
+            format!(
+                "Failed to read the Zola config at path = {}",
+                config_path.display()
+            )
+        })?;
+        toml::from_str(&contents).with_context(|| {
+            format!(
+                "Failed to parse the Zola config at path = {}",
+                config_path.display()
+            )
+        })
+    }
+
+    /// Find the `config.toml` of the Zola site which OUTPUT_DIR is part of
+    ///
+    /// Example:
+    ///     output_dir = "~/site/content/garden"
+    ///     config     = "~/site/config.toml"
+    pub fn find(output_dir: &Path) -> Option<PathBuf> {
+        output_dir
+            .ancestors()
+            .filter(|a| a.file_name().is_some_and(|name| name == "content"))
+            .filter_map(|content_dir| content_dir.parent())
+            .map(|site_dir| site_dir.join("config.toml"))
+            .find(|config_path| config_path.is_file())
+    }
+}
+
+/// OUTPUT_DIR relative to the `content/` directory (`content_dir`) of the site it is in
+///
+/// The `content/` directory next to a `config.toml` is preferred over any other directory which
+/// happens to be named "content".
+pub(crate) fn content_path(output_dir: &Path, content_dir: &Path) -> Option<PathBuf> {
+    let output_dir = std::path::absolute(output_dir).ok()?;
+    let mut content_dirs = output_dir.ancestors().filter(|a| a.ends_with(content_dir));
+    let content_dir = content_dirs
+        .clone()
+        .find(|c| {
+            c.parent()
+                .is_some_and(|site| site.join("config.toml").is_file())
+        })
+        .or_else(|| content_dirs.next())?;
+    output_dir
+        .strip_prefix(content_dir)
+        .ok()
+        .map(Path::to_path_buf)
+}
 
 pub struct ZolaFiles {
     pub output_dir: PathBuf,
@@ -14,110 +111,173 @@ impl ZolaFiles {
     pub fn new(output_dir: PathBuf) -> Self {
         Self { output_dir }
     }
-    /// write_files takes CraftDocs and writes the processed files into their intended destination
-    /// within the Zola OUTPUT_DIR
-    pub fn write_files(&self, craft_docs: CraftDocs) -> anyhow::Result<()> {
-        for (_path_rel, file_data) in craft_docs.files.iter() {
-            self.create_dir(file_data.path_slug.clone())?;
-            let output_path = self.create_output_path(&file_data.path_slug);
-            fs::write(&output_path, &file_data.contents)?;
-
-            // If this file has associated images, write them relative to the file (index.md)
-            if let Some(assets) = &file_data.assets {
-                let mut sibling_file_path_slug = output_path.clone();
-                sibling_file_path_slug.pop();
-                let abs_asset_dir = file_data.assets_dir.as_ref().expect(
-                    "There to be an asset_dir on any file_data which also has Some(Vec<Assets>)",
-                );
-                for asset in assets {
-                    let origin_asset_path = abs_asset_dir.join(asset);
-                    let destination_asset_path = sibling_file_path_slug.join(asset);
-                    fs::copy(origin_asset_path, destination_asset_path)?;
+
+    /// The path OUTPUT_DIR is mounted at inside the site's `content/` directory, internal `@/`
+    /// links start with it
+    ///
+    /// `section` (from --section) has to match OUTPUT_DIR when OUTPUT_DIR is inside a `content/`
+    /// directory. Without it the mount path is inferred from OUTPUT_DIR, falling back to its name
+    /// (which a Converter reports as Code::UnmountedOutput).
+    ///
+    /// Example:
+    ///     output_dir = "~/site/content/notes/garden" -> "notes/garden"
+    pub fn mount(output_dir: &Path, section: Option<&str>) -> anyhow::Result<String> {
+        Self::mount_in(output_dir, Path::new("content"), section)
+    }
+
+    /// ZolaFiles::mount for a site whose content directory is `content_dir`, see
+    /// Backend::content_dir
+    pub fn mount_in(
+        output_dir: &Path,
+        content_dir: &Path,
+        section: Option<&str>,
+    ) -> anyhow::Result<String> {
+        let inferred = content_path(output_dir, content_dir);
+        let mount = match (section, inferred) {
+            (Some(section), Some(inferred)) => {
+                let section = PathBuf::from(section.trim_matches('/'));
+                if section != inferred {
+                    bail!(
+                        "--section = {} does not match OUTPUT = {} which is mounted at '{}' inside \
+                        the site's content directory",
+                        section.display(),
+                        output_dir.display(),
+                        inferred.display()
+                    );
                 }
+                section
             }
+            (Some(section), None) => PathBuf::from(section.trim_matches('/')),
+            (None, Some(inferred)) => inferred,
+            (None, None) => PathBuf::from(output_dir.file_name().unwrap_or_default()),
+        };
+        let components: Vec<_> = mount
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect();
+        Ok(components.join("/"))
+    }
+    /// write_files takes CraftDocs and writes the processed files into their intended destination
+    /// within the OUTPUT_DIR, using the backend of the CraftDocs' options
+    pub fn write_files(&self, craft_docs: &CraftDocs) -> anyhow::Result<Written> {
+        craft_docs
+            .options()
+            .backend
+            .write_files(craft_docs, &self.output_dir)
+    }
+}
 
-            // If this file is NOT `index` file_name
-            // Then we should create *one* and only *one* "_index.md"
-            // for the parent dir to make it a section
-            if file_data.path_slug.file_name().unwrap() == "index.md" {
-                continue;
-            }
+/// Zola, the default Backend
+///
+/// Example link: [Dovetail Joint](@/garden/woodworking/dovetail-joint/index.md#marking-out)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Zola;
 
-            let mut parent_dir_path = file_data.path_slug.clone();
-            // Remove the file name to get the slugified path to the directory
-            parent_dir_path.pop();
-            // Append "_index.md" as a new file path
-            parent_dir_path.push("_index.md");
-            let section_file_path = self.create_output_path(&parent_dir_path);
-            // Does this file already exist (we've done this before?)
-            let exists = section_file_path.try_exists().with_context(|| {
-                format!(
-                    "Attempted to check if '{}' existed on the file system but recieved an error",
-                    section_file_path.display()
-                )
-            })?;
-            if exists {
-                continue;
-            }
-            // Create this file with the desired contents
-            let mut parent_dir_title = file_data.path_rel.clone();
-            // Remove the unslugified file name
-            parent_dir_title.pop();
-            // Get the name of the parent directory
-            let parent_dir_title = parent_dir_title.file_name().unwrap().to_str().unwrap();
-            let section_content = format!(
-                "+++\n\
-            title = \"{DIR_EMOJI} {parent_dir_title}\"\n\
-            sort_by = \"weight\"\n\
-            insert_anchor_links = \"left\"\n\
-            +++"
-            );
-
-            // Write the file
-            fs::write(&section_file_path, section_content).with_context(|| {
-                format!(
-                    "Failed to write a section _index.md file at path = {}",
-                    section_file_path.display()
-                )
-            })?;
-        }
-        // SPECIAL CASE: We assume that there are no markdown files as immediate children of out
-        // input_dir. Put another way: all files live inside a folder from the top level directory.
-        //
-        // Since this is the case we will need to generate a top level `_index.md` to mark the top
-        // level directory as a section in Zola.
-        //
-        // Since I am lazy, I am doing this as a manual special cased step.
-        let tld_section_index_md = self.output_dir.join(PathBuf::from("_index.md"));
-        let section_content = format!(
+impl Backend for Zola {
+    fn name(&self) -> &str {
+        "zola"
+    }
+
+    fn link(
+        &self,
+        mount: &str,
+        _from: &FileData,
+        to: &FileData,
+        text: &str,
+        header: Option<&str>,
+    ) -> String {
+        let destination = format!(
+            "@/{file_path}{header}",
+            file_path = Path::new(mount).join(&to.path_slug).display(),
+            header = header.unwrap_or_default(),
+        );
+        format!("[{text}]({})", link_destination(&destination))
+    }
+
+    fn frontmatter(&self, note: &FileData) -> String {
+        // Zola redirects every alias (an old URL of this note) to the note
+        format!(
+            "---\n\
+            title: \"{}\"\n\
+            date: {}\n\
+            updated: {}\n\
+            weight: {}\n\
+            {}\
+            {}\
+            extra:\n  \
+            note_type: {}\n\
+            ---\n",
+            &note.name,
+            &note.created_at,
+            note.modified_at,
+            note.note_type.to_weight(),
+            if note.draft { "draft: true\n" } else { "" },
+            yaml_aliases(note),
+            note.note_type.as_emoji(),
+        )
+    }
+
+    fn section_index(&self, title: &str, root: bool) -> String {
+        format!(
             "+++\n\
-            title = \"{DIR_EMOJI} Garden\"\n\
+            title = \"{title}\"\n\
             sort_by = \"weight\"\n\
-            template = \"garden.html\"\n\
+            {}\
             insert_anchor_links = \"left\"\n\
-            +++"
-        );
-
-        // Write the file
-        fs::write(&tld_section_index_md, section_content).with_context(|| {
-            format!(
-                "Failed to write a section _index.md file at path = {}",
-                tld_section_index_md.display()
-            )
-        })?;
-        Ok(())
+            +++",
+            if root {
+                "template = \"garden.html\"\n"
+            } else {
+                ""
+            }
+        )
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn create_output_path(&self, file_path: &PathBuf) -> PathBuf {
-        self.output_dir.join(file_path)
+    #[test]
+    fn mount_is_inferred_from_the_content_dir() {
+        let output_dir = Path::new("/site/content/notes/garden");
+        assert_eq!(ZolaFiles::mount(output_dir, None).unwrap(), "notes/garden");
+        assert_eq!(
+            ZolaFiles::mount(output_dir, Some("/notes/garden/")).unwrap(),
+            "notes/garden"
+        );
+        assert!(ZolaFiles::mount(output_dir, Some("garden")).is_err());
+        assert_eq!(
+            ZolaFiles::mount(Path::new("/site/content"), None).unwrap(),
+            ""
+        );
+        assert_eq!(
+            ZolaFiles::mount(Path::new("/tmp/export"), Some("garden")).unwrap(),
+            "garden"
+        );
     }
 
-    /// create_dir will build all necessary directories for
-    /// {output_dir}/{path_slug.pop}
-    fn create_dir(&self, mut file_path_slug: PathBuf) -> anyhow::Result<()> {
-        file_path_slug.pop();
-        let output_path = self.create_output_path(&file_path_slug);
-        fs::create_dir_all(output_path)?;
-        Ok(())
+    #[test]
+    fn aliases_are_quoted_in_the_frontmatter() {
+        let aliases = vec![
+            "/garden/dovetails/".to_string(),
+            "/garden/say \"hi\"/".to_string(),
+            "/garden/C:\\old\\".to_string(),
+        ];
+        let note = FileData {
+            name: "Dovetail Joint".into(),
+            aliases: aliases.clone(),
+            ..Default::default()
+        };
+        let frontmatter = Zola.frontmatter(&note);
+        let yaml = frontmatter
+            .strip_prefix("---\n")
+            .and_then(|f| f.strip_suffix("---\n"))
+            .unwrap();
+        let parsed: serde_yaml::Value = serde_yaml::from_str(yaml).unwrap();
+        let parsed: Vec<String> = serde_yaml::from_value(parsed["aliases"].clone()).unwrap();
+        assert_eq!(parsed, aliases);
+
+        assert!(!Zola.frontmatter(&FileData::default()).contains("aliases:"));
     }
 }